@@ -0,0 +1,391 @@
+// Goal: a general purpose arithmetic chip, so that future circuits can be written in terms of instructions
+// (add, sub, mul, div...) over copy-constrained assigned cells instead of one-off gates like the one of example3.
+// Every instruction copies its inputs into a new row and assigns the output in the same row:
+//
+//   a | b | c     | out         | q_add | q_sub | q_mul | q_div | q_mul_add
+//   x | y |       | x + y       | 1     | 0     | 0     | 0     | 0
+//   x | y | 1 / y | x / y       | 0     | 0     | 0     | 1     | 0
+//   x | y | z     | x * y + z   | 0     | 0     | 0     | 0     | 1
+//
+// For div we witness the inverse of the divisor, just like IsZeroChip does, and check that y * (1 / y) = 1
+
+use std::marker::PhantomData;
+
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    circuit::{AssignedCell, Layouter, Value},
+    plonk::{Advice, Column, ConstraintSystem, Constraints, Error, Expression, Fixed, Instance, Selector},
+    poly::Rotation,
+};
+
+// The instructions that a numeric chip exposes
+pub trait NumericInstructions<F: FieldExt> {
+    // Variable representing a number
+    type Num;
+
+    // Loads a number into the circuit as a private input
+    fn load_private(&self, layouter: impl Layouter<F>, a: Value<F>) -> Result<Self::Num, Error>;
+
+    // Loads a number into the circuit as a fixed constant
+    fn load_constant(&self, layouter: impl Layouter<F>, constant: F) -> Result<Self::Num, Error>;
+
+    // Returns `a + b`
+    fn add(&self, layouter: impl Layouter<F>, a: &Self::Num, b: &Self::Num) -> Result<Self::Num, Error>;
+
+    // Returns `a - b`
+    fn sub(&self, layouter: impl Layouter<F>, a: &Self::Num, b: &Self::Num) -> Result<Self::Num, Error>;
+
+    // Returns `a * b`
+    fn mul(&self, layouter: impl Layouter<F>, a: &Self::Num, b: &Self::Num) -> Result<Self::Num, Error>;
+
+    // Returns `a / b`, the proof can't be generated if b = 0
+    fn div(&self, layouter: impl Layouter<F>, a: &Self::Num, b: &Self::Num) -> Result<Self::Num, Error>;
+
+    // Returns `a * b + c`
+    fn mul_add(&self, layouter: impl Layouter<F>, a: &Self::Num, b: &Self::Num, c: &Self::Num) -> Result<Self::Num, Error>;
+
+    // Exposes a number as a public input to the circuit
+    fn expose_public(&self, layouter: impl Layouter<F>, num: &Self::Num, row: usize) -> Result<(), Error>;
+}
+
+#[derive(Debug, Clone)]
+pub struct ArithmeticConfig {
+    a: Column<Advice>,
+    b: Column<Advice>,
+    c: Column<Advice>,
+    out: Column<Advice>,
+    instance: Column<Instance>,
+    q_add: Selector,
+    q_sub: Selector,
+    q_mul: Selector,
+    q_div: Selector,
+    q_mul_add: Selector,
+}
+
+pub struct ArithmeticChip<F: FieldExt> {
+    config: ArithmeticConfig,
+    _marker: PhantomData<F>,
+}
+
+impl<F: FieldExt> ArithmeticChip<F> {
+    pub fn construct(config: ArithmeticConfig) -> Self {
+        Self {
+            config,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn configure(
+        meta: &mut ConstraintSystem<F>,
+        advice: [Column<Advice>; 4],
+        instance: Column<Instance>,
+        constant: Column<Fixed>,
+    ) -> ArithmeticConfig {
+        let [a, b, c, out] = advice;
+        for column in advice {
+            meta.enable_equality(column);
+        }
+        meta.enable_equality(instance);
+        // constants are assigned to advice cells and constrained to this fixed column
+        meta.enable_constant(constant);
+
+        let q_add = meta.selector();
+        let q_sub = meta.selector();
+        let q_mul = meta.selector();
+        let q_div = meta.selector();
+        let q_mul_add = meta.selector();
+
+        meta.create_gate("add", |meta| {
+            let q_add = meta.query_selector(q_add);
+            let a = meta.query_advice(a, Rotation::cur());
+            let b = meta.query_advice(b, Rotation::cur());
+            let out = meta.query_advice(out, Rotation::cur());
+            Constraints::with_selector(q_add, [("out = a + b", out - (a + b))])
+        });
+
+        meta.create_gate("sub", |meta| {
+            let q_sub = meta.query_selector(q_sub);
+            let a = meta.query_advice(a, Rotation::cur());
+            let b = meta.query_advice(b, Rotation::cur());
+            let out = meta.query_advice(out, Rotation::cur());
+            Constraints::with_selector(q_sub, [("out = a - b", out - (a - b))])
+        });
+
+        meta.create_gate("mul", |meta| {
+            let q_mul = meta.query_selector(q_mul);
+            let a = meta.query_advice(a, Rotation::cur());
+            let b = meta.query_advice(b, Rotation::cur());
+            let out = meta.query_advice(out, Rotation::cur());
+            Constraints::with_selector(q_mul, [("out = a * b", out - a * b)])
+        });
+
+        // c is the inverse of b. If b = 0 there's no c such that b * c = 1, so the prover can't cheat
+        meta.create_gate("div", |meta| {
+            let q_div = meta.query_selector(q_div);
+            let a = meta.query_advice(a, Rotation::cur());
+            let b = meta.query_advice(b, Rotation::cur());
+            let b_inv = meta.query_advice(c, Rotation::cur());
+            let out = meta.query_advice(out, Rotation::cur());
+            Constraints::with_selector(
+                q_div,
+                [
+                    ("b * b_inv = 1", b * b_inv.clone() - Expression::Constant(F::one())),
+                    ("out = a * b_inv", out - a * b_inv),
+                ],
+            )
+        });
+
+        meta.create_gate("mul_add", |meta| {
+            let q_mul_add = meta.query_selector(q_mul_add);
+            let a = meta.query_advice(a, Rotation::cur());
+            let b = meta.query_advice(b, Rotation::cur());
+            let c = meta.query_advice(c, Rotation::cur());
+            let out = meta.query_advice(out, Rotation::cur());
+            Constraints::with_selector(q_mul_add, [("out = a * b + c", out - (a * b + c))])
+        });
+
+        ArithmeticConfig {
+            a,
+            b,
+            c,
+            out,
+            instance,
+            q_add,
+            q_sub,
+            q_mul,
+            q_div,
+            q_mul_add,
+        }
+    }
+
+    // Native reference of div: None when b = 0, the circuit can't be proven then
+    pub fn native_div(a: F, b: F) -> Option<F> {
+        Option::from(b.invert()).map(|b_inv: F| a * b_inv)
+    }
+
+    // Copy a and b into a new row and assign out = op(a, b)
+    fn binary_op(
+        &self,
+        mut layouter: impl Layouter<F>,
+        name: &str,
+        selector: Selector,
+        a: &AssignedCell<F, F>,
+        b: &AssignedCell<F, F>,
+        op: impl Fn(F, F) -> F,
+    ) -> Result<AssignedCell<F, F>, Error> {
+        layouter.assign_region(
+            || name,
+            |mut region| {
+                selector.enable(&mut region, 0)?;
+                a.copy_advice(|| "a", &mut region, self.config.a, 0)?;
+                b.copy_advice(|| "b", &mut region, self.config.b, 0)?;
+                let out = a.value().zip(b.value()).map(|(a, b)| op(*a, *b));
+                region.assign_advice(|| "out", self.config.out, 0, || out)
+            },
+        )
+    }
+}
+
+impl<F: FieldExt> NumericInstructions<F> for ArithmeticChip<F> {
+    type Num = AssignedCell<F, F>;
+
+    fn load_private(&self, mut layouter: impl Layouter<F>, a: Value<F>) -> Result<Self::Num, Error> {
+        layouter.assign_region(
+            || "load private",
+            |mut region| region.assign_advice(|| "private input", self.config.a, 0, || a),
+        )
+    }
+
+    fn load_constant(&self, mut layouter: impl Layouter<F>, constant: F) -> Result<Self::Num, Error> {
+        layouter.assign_region(
+            || "load constant",
+            |mut region| region.assign_advice_from_constant(|| "constant value", self.config.a, 0, constant),
+        )
+    }
+
+    fn add(&self, layouter: impl Layouter<F>, a: &Self::Num, b: &Self::Num) -> Result<Self::Num, Error> {
+        self.binary_op(layouter, "add", self.config.q_add, a, b, |a, b| a + b)
+    }
+
+    fn sub(&self, layouter: impl Layouter<F>, a: &Self::Num, b: &Self::Num) -> Result<Self::Num, Error> {
+        self.binary_op(layouter, "sub", self.config.q_sub, a, b, |a, b| a - b)
+    }
+
+    fn mul(&self, layouter: impl Layouter<F>, a: &Self::Num, b: &Self::Num) -> Result<Self::Num, Error> {
+        self.binary_op(layouter, "mul", self.config.q_mul, a, b, |a, b| a * b)
+    }
+
+    fn div(&self, mut layouter: impl Layouter<F>, a: &Self::Num, b: &Self::Num) -> Result<Self::Num, Error> {
+        // there's no inverse to witness for b = 0, that's a witness generation error like in InverseChip
+        b.value().error_if_known_and(|b| bool::from(b.is_zero()))?;
+
+        layouter.assign_region(
+            || "div",
+            |mut region| {
+                self.config.q_div.enable(&mut region, 0)?;
+                a.copy_advice(|| "a", &mut region, self.config.a, 0)?;
+                b.copy_advice(|| "b", &mut region, self.config.b, 0)?;
+                let b_inv = b.value().map(|b| b.invert().unwrap());
+                region.assign_advice(|| "b inv", self.config.c, 0, || b_inv)?;
+                let out = a.value().zip(b_inv).map(|(a, b_inv)| *a * b_inv);
+                region.assign_advice(|| "out", self.config.out, 0, || out)
+            },
+        )
+    }
+
+    fn mul_add(&self, mut layouter: impl Layouter<F>, a: &Self::Num, b: &Self::Num, c: &Self::Num) -> Result<Self::Num, Error> {
+        layouter.assign_region(
+            || "mul_add",
+            |mut region| {
+                self.config.q_mul_add.enable(&mut region, 0)?;
+                a.copy_advice(|| "a", &mut region, self.config.a, 0)?;
+                b.copy_advice(|| "b", &mut region, self.config.b, 0)?;
+                c.copy_advice(|| "c", &mut region, self.config.c, 0)?;
+                let out = a.value().zip(b.value()).zip(c.value()).map(|((a, b), c)| *a * b + c);
+                region.assign_advice(|| "out", self.config.out, 0, || out)
+            },
+        )
+    }
+
+    fn expose_public(&self, mut layouter: impl Layouter<F>, num: &Self::Num, row: usize) -> Result<(), Error> {
+        layouter.constrain_instance(num.cell(), self.config.instance, row)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use halo2_proofs::{circuit::SimpleFloorPlanner, dev::MockProver, pasta::Fp, plonk::Circuit};
+
+    // out = (x * y + z) / (x - y) - 3
+    #[derive(Default)]
+    struct MyCircuit<F> {
+        x: Value<F>,
+        y: Value<F>,
+        z: Value<F>,
+    }
+
+    impl<F: FieldExt> Circuit<F> for MyCircuit<F> {
+        type Config = ArithmeticConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            let advice = [
+                meta.advice_column(),
+                meta.advice_column(),
+                meta.advice_column(),
+                meta.advice_column(),
+            ];
+            let instance = meta.instance_column();
+            let constant = meta.fixed_column();
+            ArithmeticChip::configure(meta, advice, instance, constant)
+        }
+
+        fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<F>) -> Result<(), Error> {
+            let chip = ArithmeticChip::construct(config);
+
+            let x = chip.load_private(layouter.namespace(|| "load x"), self.x)?;
+            let y = chip.load_private(layouter.namespace(|| "load y"), self.y)?;
+            let z = chip.load_private(layouter.namespace(|| "load z"), self.z)?;
+            let three = chip.load_constant(layouter.namespace(|| "load 3"), F::from(3))?;
+
+            let xy_z = chip.mul_add(layouter.namespace(|| "x * y + z"), &x, &y, &z)?;
+            let x_y = chip.sub(layouter.namespace(|| "x - y"), &x, &y)?;
+            let div = chip.div(layouter.namespace(|| "(x * y + z) / (x - y)"), &xy_z, &x_y)?;
+            let out = chip.sub(layouter.namespace(|| "- 3"), &div, &three)?;
+            // (x + y) * 1 is just to exercise add and mul
+            let one = chip.load_constant(layouter.namespace(|| "load 1"), F::one())?;
+            let x_plus_y = chip.add(layouter.namespace(|| "x + y"), &x, &y)?;
+            let x_plus_y = chip.mul(layouter.namespace(|| "(x + y) * 1"), &x_plus_y, &one)?;
+
+            chip.expose_public(layouter.namespace(|| "expose out"), &out, 0)?;
+            chip.expose_public(layouter.namespace(|| "expose x + y"), &x_plus_y, 1)
+        }
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let k = 5;
+
+        // (4 * 2 + 2) / (4 - 2) - 3 = 2
+        let circuit = MyCircuit {
+            x: Value::known(Fp::from(4)),
+            y: Value::known(Fp::from(2)),
+            z: Value::known(Fp::from(2)),
+        };
+        let prover = MockProver::run(k, &circuit, vec![vec![Fp::from(2), Fp::from(6)]]).unwrap();
+        prover.assert_satisfied();
+        crate::dev_tools::assert_fully_constrained(k, &circuit, vec![vec![Fp::from(2), Fp::from(6)]]);
+
+        let prover = MockProver::run(k, &circuit, vec![vec![Fp::from(3), Fp::from(6)]]).unwrap();
+        assert!(prover.verify().is_err());
+
+        // division by zero, the witness can't even be generated
+        let circuit = MyCircuit {
+            x: Value::known(Fp::from(2)),
+            y: Value::known(Fp::from(2)),
+            z: Value::known(Fp::from(2)),
+        };
+        assert!(MockProver::run(k, &circuit, vec![vec![-Fp::from(3), Fp::from(4)]]).is_err());
+    }
+
+    #[test]
+    fn test_arithmetic_reference() {
+        use crate::dev_tools::check_output;
+
+        check_output(
+            5,
+            0,
+            16,
+            |rng| (rng.field(), rng.field(), rng.field()),
+            |&(x, y, z): &(Fp, Fp, Fp)| {
+                let div = ArithmeticChip::native_div(x * y + z, x - y).expect("x = y");
+                (div - Fp::from(3), x + y)
+            },
+            // a wrong out, x + y is right
+            |rng, &(out, sum)| (out + Fp::from(1 + rng.below(16) as u64), sum),
+            |&(x, y, z), &(out, sum)| {
+                let circuit = MyCircuit {
+                    x: Value::known(x),
+                    y: Value::known(y),
+                    z: Value::known(z),
+                };
+                (circuit, vec![vec![out, sum]])
+            },
+        );
+    }
+
+    #[test]
+    fn test_arithmetic_forged_witness() {
+        use crate::dev_tools::{Forgeries, Rejection};
+
+        let circuit = || MyCircuit {
+            x: Value::known(Fp::from(4)),
+            y: Value::known(Fp::from(2)),
+            z: Value::known(Fp::from(2)),
+        };
+        let forgeries = Forgeries::new(circuit, vec![vec![Fp::from(2), Fp::from(6)]]);
+
+        // wrong outputs, column 3 is out. The first sub is x - y
+        forgeries
+            .forge("mul_add", 3, 0, Fp::from(11))
+            .assert_rejected(Rejection::Gate("mul_add"));
+        forgeries
+            .forge_nth("sub", 0, 3, 0, Fp::zero())
+            .assert_rejected(Rejection::Gate("sub"));
+
+        // b_inv = 0 would allow dividing by zero, column 2 is c
+        forgeries
+            .forge("div", 2, 0, Fp::zero())
+            .assert_rejected(Rejection::Gate("div"));
+
+        // a constant that isn't the fixed one, the first constant is 3
+        forgeries
+            .forge_nth("load constant", 0, 0, 0, Fp::from(4))
+            .assert_rejected(Rejection::Permutation);
+    }
+}
@@ -0,0 +1,415 @@
+// Goal: decompose an assigned value into n boolean cells, to_bits(cell, n).
+// Every bit is constrained to be boolean with b * (1 - b) = 0, that's the range check expression of example4 for a range of 2.
+// The bits are placed most significant first and a running sum proves the recomposition:
+//   acc_0 = b_0
+//   acc_i = 2 * acc_{i-1} + b_i
+// so that the accumulator of the last row is the value itself.
+//
+// When n is the full bit length of the field there's a catch: the bits could encode value + p, which is the same field element!
+// To avoid this aliasing attack we also prove that the bits encode an integer smaller than p,
+// comparing them with the bits m_i of p - 1 from the most significant one. eq_i is 1 as long as the bits are equal to the prefix of p - 1:
+//   eq_i = eq_{i-1} * (m_i * b_i + (1 - m_i) * (1 - b_i))
+//   eq_{i-1} * (1 - m_i) * b_i = 0   <- if the prefix is the same as p - 1, b_i can't be 1 where m_i is 0
+//
+//   bit | acc | m   | eq  | q_bit | q_acc_first | q_acc | q_canonical_first | q_canonical
+//   b_0 | ... | m_0 | ... | 1     | 1           | 0     | 1                 | 0
+//   b_1 | ... | m_1 | ... | 1     | 0           | 1     | 0                 | 1
+
+use std::marker::PhantomData;
+
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    circuit::{AssignedCell, Layouter, Value},
+    plonk::{Advice, Column, ConstraintSystem, Constraints, Error, Expression, Fixed, Selector},
+    poly::Rotation,
+};
+
+// Little-endian bits of the canonical representation of a field element (up to 256 bits)
+pub fn to_le_bits<F: FieldExt>(value: &F) -> Vec<bool> {
+    let lo = value.get_lower_128();
+    // value - lo is a multiple of 2^128, so dividing it by 2^128 in the field gives back the integer quotient
+    let two_pow_128 = F::from_u128(1 << 64) * F::from_u128(1 << 64);
+    let hi = ((*value - F::from_u128(lo)) * two_pow_128.invert().unwrap()).get_lower_128();
+    (0..256)
+        .map(|i| if i < 128 { (lo >> i) & 1 == 1 } else { (hi >> (i - 128)) & 1 == 1 })
+        .collect()
+}
+
+// Little-endian bits of p - 1, trimmed to the bit length of the modulus
+fn modulus_minus_one_bits<F: FieldExt>() -> Vec<bool> {
+    let mut bits = to_le_bits(&-F::one());
+    while bits.last() == Some(&false) {
+        bits.pop();
+    }
+    bits
+}
+
+#[derive(Debug, Clone)]
+pub struct BitDecompositionConfig {
+    bit: Column<Advice>,
+    acc: Column<Advice>,
+    m: Column<Fixed>,
+    eq: Column<Advice>,
+    q_bit: Selector,
+    q_acc_first: Selector,
+    q_acc: Selector,
+    q_canonical_first: Selector,
+    q_canonical: Selector,
+}
+
+pub struct BitDecompositionChip<F: FieldExt> {
+    config: BitDecompositionConfig,
+    // bits of p - 1, little-endian
+    modulus_bits: Vec<bool>,
+    _marker: PhantomData<F>,
+}
+
+impl<F: FieldExt> BitDecompositionChip<F> {
+    pub fn construct(config: BitDecompositionConfig) -> Self {
+        Self {
+            config,
+            modulus_bits: modulus_minus_one_bits::<F>(),
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn configure(meta: &mut ConstraintSystem<F>) -> BitDecompositionConfig {
+        let bit = meta.advice_column();
+        let acc = meta.advice_column();
+        let m = meta.fixed_column();
+        let eq = meta.advice_column();
+        meta.enable_equality(bit);
+        meta.enable_equality(acc);
+
+        let q_bit = meta.selector();
+        let q_acc_first = meta.selector();
+        let q_acc = meta.selector();
+        let q_canonical_first = meta.selector();
+        let q_canonical = meta.selector();
+
+        let one = || Expression::Constant(F::one());
+
+        meta.create_gate("bool", |meta| {
+            let q_bit = meta.query_selector(q_bit);
+            let b = meta.query_advice(bit, Rotation::cur());
+            Constraints::with_selector(q_bit, [("bool", b.clone() * (one() - b))])
+        });
+
+        meta.create_gate("bits acc first", |meta| {
+            let q_acc_first = meta.query_selector(q_acc_first);
+            let b = meta.query_advice(bit, Rotation::cur());
+            let acc = meta.query_advice(acc, Rotation::cur());
+            Constraints::with_selector(q_acc_first, [("acc_0 = b_0", acc - b)])
+        });
+
+        meta.create_gate("bits acc", |meta| {
+            let q_acc = meta.query_selector(q_acc);
+            let b = meta.query_advice(bit, Rotation::cur());
+            let acc_prev = meta.query_advice(acc, Rotation::prev());
+            let acc = meta.query_advice(acc, Rotation::cur());
+            Constraints::with_selector(
+                q_acc,
+                [("acc_i = 2 * acc_{i-1} + b_i", acc - (acc_prev * Expression::Constant(F::from(2)) + b))],
+            )
+        });
+
+        // 1 if b_i is equal to m_i, 0 otherwise
+        let same_bit = |b: Expression<F>, m: Expression<F>| m.clone() * b.clone() + (one() - m) * (one() - b);
+
+        // eq_{-1} = 1 so the first row doesn't need the previous eq
+        meta.create_gate("canonical first", |meta| {
+            let q_canonical_first = meta.query_selector(q_canonical_first);
+            let b = meta.query_advice(bit, Rotation::cur());
+            let m = meta.query_fixed(m, Rotation::cur());
+            let eq = meta.query_advice(eq, Rotation::cur());
+            Constraints::with_selector(
+                q_canonical_first,
+                [
+                    ("b_0 <= m_0", (one() - m.clone()) * b.clone()),
+                    ("eq_0", eq - same_bit(b, m)),
+                ],
+            )
+        });
+
+        meta.create_gate("canonical", |meta| {
+            let q_canonical = meta.query_selector(q_canonical);
+            let b = meta.query_advice(bit, Rotation::cur());
+            let m = meta.query_fixed(m, Rotation::cur());
+            let eq_prev = meta.query_advice(eq, Rotation::prev());
+            let eq = meta.query_advice(eq, Rotation::cur());
+            Constraints::with_selector(
+                q_canonical,
+                [
+                    ("b_i <= m_i", eq_prev.clone() * (one() - m.clone()) * b.clone()),
+                    ("eq_i", eq - eq_prev * same_bit(b, m)),
+                ],
+            )
+        });
+
+        BitDecompositionConfig {
+            bit,
+            acc,
+            m,
+            eq,
+            q_bit,
+            q_acc_first,
+            q_acc,
+            q_canonical_first,
+            q_canonical,
+        }
+    }
+
+    // Native reference: the num_bits little-endian bits of value, None if it doesn't fit
+    pub fn native(value: F, num_bits: usize) -> Option<Vec<bool>> {
+        let bits = to_le_bits(&value);
+        bits[num_bits..].iter().all(|bit| !bit).then(|| bits[..num_bits].to_vec())
+    }
+
+    // Decompose `value` into `num_bits` boolean cells, returned little-endian.
+    // If num_bits is the bit length of the field the bits are also proven to be the canonical representation of value
+    pub fn to_bits(
+        &self,
+        mut layouter: impl Layouter<F>,
+        value: &AssignedCell<F, F>,
+        num_bits: usize,
+    ) -> Result<Vec<AssignedCell<F, F>>, Error> {
+        let field_bits = self.modulus_bits.len();
+        assert!(num_bits > 0 && num_bits <= field_bits);
+        // a shorter decomposition encodes a value < 2^num_bits < p so it can't alias
+        let canonical = num_bits == field_bits;
+
+        let bits = value.value().map(to_le_bits);
+        // bit and running sum of row i, rows go from the most significant bit
+        let bit = |i: usize| bits.as_ref().map(|bits| bits[num_bits - 1 - i]);
+        let acc = |i: usize| {
+            bits.as_ref().map(|bits| {
+                bits[num_bits - 1 - i..num_bits]
+                    .iter()
+                    .rev()
+                    .fold(F::zero(), |acc, bit| acc.double() + F::from(*bit as u64))
+            })
+        };
+        let eq = |i: usize| {
+            bits.as_ref().map(|bits| {
+                let same = (0..=i).all(|j| bits[num_bits - 1 - j] == self.modulus_bits[num_bits - 1 - j]);
+                F::from(same as u64)
+            })
+        };
+
+        layouter.assign_region(
+            || "to bits",
+            |mut region| {
+                let mut bit_cells = vec![];
+                let mut acc_cell = None;
+
+                for i in 0..num_bits {
+                    self.config.q_bit.enable(&mut region, i)?;
+                    if i == 0 {
+                        self.config.q_acc_first.enable(&mut region, i)?;
+                    } else {
+                        self.config.q_acc.enable(&mut region, i)?;
+                    }
+
+                    let b = bit(i).map(|b| F::from(b as u64));
+                    bit_cells.push(region.assign_advice(|| format!("bit {}", num_bits - 1 - i), self.config.bit, i, || b)?);
+                    acc_cell = Some(region.assign_advice(|| "acc", self.config.acc, i, || acc(i))?);
+
+                    if canonical {
+                        if i == 0 {
+                            self.config.q_canonical_first.enable(&mut region, i)?;
+                        } else {
+                            self.config.q_canonical.enable(&mut region, i)?;
+                        }
+                        let m = F::from(self.modulus_bits[num_bits - 1 - i] as u64);
+                        region.assign_fixed(|| "m", self.config.m, i, || Value::known(m))?;
+                        region.assign_advice(|| "eq", self.config.eq, i, || eq(i))?;
+                    }
+                }
+
+                // the last accumulator is the value itself
+                region.constrain_equal(value.cell(), acc_cell.unwrap().cell())?;
+
+                bit_cells.reverse();
+                Ok(bit_cells)
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use halo2_proofs::{
+        circuit::SimpleFloorPlanner,
+        pasta::Fp,
+        plonk::Circuit,
+    };
+
+    use crate::dev_tools::{min_k, mock_prove};
+
+    #[derive(Default)]
+    struct MyCircuit<F> {
+        value: Value<F>,
+        num_bits: usize,
+    }
+
+    impl<F: FieldExt> Circuit<F> for MyCircuit<F> {
+        type Config = (Column<Advice>, BitDecompositionConfig);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self {
+                value: Value::unknown(),
+                num_bits: self.num_bits,
+            }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            let value = meta.advice_column();
+            meta.enable_equality(value);
+            (value, BitDecompositionChip::configure(meta))
+        }
+
+        fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<F>) -> Result<(), Error> {
+            let chip = BitDecompositionChip::construct(config.1);
+            let value = layouter.assign_region(
+                || "load value",
+                |mut region| region.assign_advice(|| "value", config.0, 0, || self.value),
+            )?;
+            chip.to_bits(layouter.namespace(|| "to bits"), &value, self.num_bits)?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_to_bits() {
+        let circuit = MyCircuit {
+            value: Value::known(Fp::from(0x2ab)),
+            num_bits: 10,
+        };
+        mock_prove(&circuit, vec![]).unwrap().assert_satisfied();
+        crate::dev_tools::assert_fully_constrained(min_k(&circuit, &[]).unwrap(), &circuit, vec![]);
+
+        // 0x2ab doesn't fit into 4 bits
+        let circuit = MyCircuit {
+            value: Value::known(Fp::from(0x2ab)),
+            num_bits: 4,
+        };
+        assert!(mock_prove(&circuit, vec![]).unwrap().verify().is_err());
+
+        // p - 1 is the largest canonical value, the full width decomposition of pasta is 255 rows
+        let circuit = MyCircuit {
+            value: Value::known(-Fp::one()),
+            num_bits: 255,
+        };
+        mock_prove(&circuit, vec![]).unwrap().assert_satisfied();
+    }
+
+    #[test]
+    fn test_to_bits_reference() {
+        use crate::dev_tools::check_acceptance;
+
+        check_acceptance(
+            9,
+            0,
+            16,
+            // values of a few bits and spread over the field, and the full width decomposition from time to time
+            |rng| {
+                let value: Fp = match rng.below(2) {
+                    0 => Fp::from(rng.below(1 << 12) as u64),
+                    _ => rng.field(),
+                };
+                let num_bits = if rng.below(4) == 0 { 255 } else { 1 + rng.below(16) };
+                (value, num_bits)
+            },
+            |&(value, num_bits)| BitDecompositionChip::native(value, num_bits).is_some(),
+            |&(value, num_bits)| {
+                let circuit = MyCircuit {
+                    value: Value::known(value),
+                    num_bits,
+                };
+                (circuit, vec![])
+            },
+        );
+        assert_eq!(
+            BitDecompositionChip::native(Fp::from(0b1011), 4),
+            Some(vec![true, true, false, true])
+        );
+    }
+
+    #[test]
+    fn test_to_bits_forged_witness() {
+        use crate::dev_tools::{Rejection, Tampered};
+
+        // 0x2ab = ...0 1 1, rows 8 and 9 hold the two last bits. 2 * 340 + 3 still recomposes 0x2ab
+        // with the bits 0 and 3, only the boolean constraint catches it. Column 1 is bit, column 2 is acc
+        let circuit = MyCircuit {
+            value: Value::known(Fp::from(0x2ab)),
+            num_bits: 10,
+        };
+        let k = min_k(&circuit, &[]).unwrap();
+        Tampered::new(circuit)
+            .forge("to bits", 1, 8, Fp::zero())
+            .forge("to bits", 2, 8, Fp::from(340))
+            .forge("to bits", 1, 9, Fp::from(3))
+            .assert_rejected(k, vec![], Rejection::Gate("bool"));
+
+        // eq_0 = 0 would stop the canonical comparison with p - 1 from the first bit, column 3 is eq
+        let circuit = MyCircuit {
+            value: Value::known(-Fp::one()),
+            num_bits: 255,
+        };
+        let k = min_k(&circuit, &[]).unwrap();
+        Tampered::new(circuit)
+            .forge("to bits", 3, 0, Fp::zero())
+            .assert_rejected(k, vec![], Rejection::Gate("canonical first"));
+    }
+
+    #[test]
+    fn test_to_bits_forged_aliasing() {
+        use crate::dev_tools::{Rejection, Tampered};
+
+        let num_bits = 255;
+        let v = 5;
+
+        // the bits of the integer v + p: p - 1 plus v + 1, it's still smaller than 2^255
+        let mut bits = to_le_bits(&-Fp::one());
+        let (mut n, mut carry) = (v + 1, 0);
+        for bit in bits.iter_mut() {
+            let sum = *bit as u64 + (n & 1) + carry;
+            *bit = sum & 1 == 1;
+            carry = sum >> 1;
+            n >>= 1;
+        }
+        assert!(!bits[num_bits..].iter().any(|bit| *bit));
+
+        // the whole column is consistent: booleans, a running sum that recomposes v + p = v and eq computed from the forged bits,
+        // so only the comparison with the bits of p - 1 can tell. Columns 1, 2 and 3 are bit, acc and eq
+        let modulus_bits = modulus_minus_one_bits::<Fp>();
+        let circuit = MyCircuit {
+            value: Value::known(Fp::from(v)),
+            num_bits,
+        };
+        let k = min_k(&circuit, &[]).unwrap();
+        let mut tampered = Tampered::new(circuit);
+        let (mut acc, mut eq) = (Fp::zero(), true);
+        for row in 0..num_bits {
+            let i = num_bits - 1 - row;
+            acc = acc + acc + Fp::from(bits[i] as u64);
+            eq = eq && bits[i] == modulus_bits[i];
+            tampered = tampered
+                .forge("to bits", 1, row, Fp::from(bits[i] as u64))
+                .forge("to bits", 2, row, acc)
+                .forge("to bits", 3, row, Fp::from(eq as u64));
+        }
+        assert_eq!(acc, Fp::from(v));
+
+        let failures = tampered.verify(k, vec![]).unwrap().unwrap_err();
+        assert!(
+            failures.iter().all(|failure| Rejection::Gate("canonical").matches(failure)),
+            "{:#?}",
+            failures
+        );
+        tampered.assert_rejected(k, vec![], Rejection::Gate("canonical"));
+    }
+}
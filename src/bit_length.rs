@@ -0,0 +1,317 @@
+// Goal: return the bit length of an assigned value as an assigned cell.
+// The tagged table of example6 maps every byte to its exact bit length (num_bits, value), with 0 taking 1 bit.
+// Wider values are first split into bytes with ByteDecompositionChip, then we walk the bytes from the most significant one:
+// the bit length is the bit length of the first non zero byte, plus 8 for every byte below it.
+//   found_i = found_{i-1} + (1 - found_{i-1}) * (byte_i != 0)
+//   len_i   = found_{i-1} * (len_{i-1} + 8) + (1 - found_{i-1}) * bits_i
+// Leading zero bytes keep len at 1 until the first non zero byte resets it, so 0 has bit length 1 like in the table.
+//
+// e.g. NUM_BYTES = 3, value = 0x0105ff
+//   byte | bits | found | len | byte_inv  | q_lookup | q_first | q_step
+//   0x01 | 1    | 1     | 1   | 1 / 0x01  | 1        | 1       | 0
+//   0x05 | 3    | 1     | 9   | 1 / 0x05  | 1        | 0       | 1
+//   0xff | 8    | 1     | 17  | 1 / 0xff  | 1        | 0       | 1
+
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    circuit::{AssignedCell, Layouter, Value},
+    plonk::{Advice, Column, ConstraintSystem, Constraints, Error, Expression, Selector},
+    poly::Rotation,
+};
+
+use crate::byte_decomposition::{ByteDecompositionChip, ByteDecompositionConfig};
+use crate::is_zero::{IsZeroChip, IsZeroConfig, IsZeroInstructions};
+use crate::range_check::table::RangeCheckTable as TaggedRangeCheckTable;
+
+// bit length of a byte as in the tagged table, 0 takes 1 bit. It's also right for any u64
+fn byte_bit_length(byte: u64) -> u64 {
+    if byte == 0 {
+        1
+    } else {
+        (u64::BITS - byte.leading_zeros()) as u64
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BitLengthConfig<F: FieldExt, const NUM_BYTES: usize> {
+    byte: Column<Advice>,
+    bits: Column<Advice>,
+    found: Column<Advice>,
+    len: Column<Advice>,
+    q_lookup: Selector,
+    q_first: Selector,
+    q_step: Selector,
+    byte_is_zero: IsZeroConfig<F>,
+    table: TaggedRangeCheckTable<F, 8, 256>,
+    decomposition: ByteDecompositionConfig<F, NUM_BYTES>,
+}
+
+pub struct BitLengthChip<F: FieldExt, const NUM_BYTES: usize> {
+    config: BitLengthConfig<F, NUM_BYTES>,
+}
+
+impl<F: FieldExt, const NUM_BYTES: usize> BitLengthChip<F, NUM_BYTES> {
+    pub fn construct(config: BitLengthConfig<F, NUM_BYTES>) -> Self {
+        Self { config }
+    }
+
+    pub fn configure(meta: &mut ConstraintSystem<F>, advice: [Column<Advice>; 6]) -> BitLengthConfig<F, NUM_BYTES> {
+        let [value, byte, bits, found, len, byte_inv] = advice;
+        meta.enable_equality(len);
+
+        // the decomposition also enables equality on value and byte
+        let decomposition = ByteDecompositionChip::configure(meta, value, byte);
+
+        // Simple selectors cannot appear in lookup arguments
+        let q_lookup = meta.complex_selector();
+        let q_first = meta.selector();
+        let q_step = meta.selector();
+        let table = TaggedRangeCheckTable::configure(meta);
+
+        // bits is the exact bit length of byte.
+        // Disabled rows look up (1, 0), which is a row of the table, rather than (0, 0) which is not
+        meta.lookup(|meta| {
+            let q_lookup = meta.query_selector(q_lookup);
+            let not_q_lookup = Expression::Constant(F::one()) - q_lookup.clone();
+            let byte = meta.query_advice(byte, Rotation::cur());
+            let bits = meta.query_advice(bits, Rotation::cur());
+            vec![
+                (q_lookup.clone() * bits + not_q_lookup, table.num_bits),
+                (q_lookup * byte, table.value),
+            ]
+        });
+
+        let byte_is_zero = IsZeroChip::configure(
+            meta,
+            |meta| meta.query_selector(q_lookup),
+            |meta| meta.query_advice(byte, Rotation::cur()),
+            byte_inv,
+        );
+
+        let one = || Expression::Constant(F::one());
+
+        meta.create_gate("bit length first", |meta| {
+            let q_first = meta.query_selector(q_first);
+            let bits = meta.query_advice(bits, Rotation::cur());
+            let found = meta.query_advice(found, Rotation::cur());
+            let len = meta.query_advice(len, Rotation::cur());
+            Constraints::with_selector(
+                q_first,
+                [
+                    ("found_0 = byte_0 != 0", found - (one() - byte_is_zero.expr())),
+                    ("len_0 = bits_0", len - bits),
+                ],
+            )
+        });
+
+        meta.create_gate("bit length step", |meta| {
+            let q_step = meta.query_selector(q_step);
+            let bits = meta.query_advice(bits, Rotation::cur());
+            let found_prev = meta.query_advice(found, Rotation::prev());
+            let found = meta.query_advice(found, Rotation::cur());
+            let len_prev = meta.query_advice(len, Rotation::prev());
+            let len = meta.query_advice(len, Rotation::cur());
+            let not_found_prev = one() - found_prev.clone();
+            Constraints::with_selector(
+                q_step,
+                [
+                    (
+                        "found_i = found_{i-1} or byte_i != 0",
+                        found - (found_prev.clone() + not_found_prev.clone() * (one() - byte_is_zero.expr())),
+                    ),
+                    (
+                        "len_i = len_{i-1} + 8 once found, bits_i before",
+                        len - (found_prev * (len_prev + Expression::Constant(F::from(8))) + not_found_prev * bits),
+                    ),
+                ],
+            )
+        });
+
+        BitLengthConfig {
+            byte,
+            bits,
+            found,
+            len,
+            q_lookup,
+            q_first,
+            q_step,
+            byte_is_zero,
+            table,
+            decomposition,
+        }
+    }
+
+    // Native reference: the bit length of value, 0 takes 1 bit like in the table
+    pub fn native(value: u64) -> u64 {
+        byte_bit_length(value)
+    }
+
+    // Load the lookup tables, this must be called once per circuit
+    pub fn load_table(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
+        ByteDecompositionChip::construct(self.config.decomposition.clone()).load_table(layouter)?;
+        self.config.table.load(layouter)
+    }
+
+    // Returns the bit length of value, which must fit in NUM_BYTES bytes
+    pub fn bit_length(&self, mut layouter: impl Layouter<F>, value: &AssignedCell<F, F>) -> Result<AssignedCell<F, F>, Error> {
+        let decomposition = ByteDecompositionChip::construct(self.config.decomposition.clone());
+        let is_zero_chip = IsZeroChip::construct(self.config.byte_is_zero.clone());
+
+        let bytes = decomposition.decompose(layouter.namespace(|| "decompose"), value)?;
+
+        layouter.assign_region(
+            || "bit length",
+            |mut region| {
+                let mut found = Value::known(false);
+                let mut len = Value::known(0u64);
+                let mut len_cell = None;
+
+                // most significant byte first
+                for (row, byte) in bytes.iter().rev().enumerate() {
+                    self.config.q_lookup.enable(&mut region, row)?;
+                    if row == 0 {
+                        self.config.q_first.enable(&mut region, row)?;
+                    } else {
+                        self.config.q_step.enable(&mut region, row)?;
+                    }
+
+                    byte.copy_advice(|| "byte", &mut region, self.config.byte, row)?;
+                    is_zero_chip.assign(&mut region, row, byte.value().copied())?;
+
+                    let byte = byte.value().map(|byte| byte.get_lower_128() as u64);
+                    let bits = byte.map(byte_bit_length);
+                    len = found.zip(len).zip(bits).map(|((found, len), bits)| if found { len + 8 } else { bits });
+                    found = found.zip(byte).map(|(found, byte)| found || byte != 0);
+
+                    region.assign_advice(|| "bits", self.config.bits, row, || bits.map(F::from))?;
+                    region.assign_advice(|| "found", self.config.found, row, || found.map(|found| F::from(found as u64)))?;
+                    len_cell = Some(region.assign_advice(|| "len", self.config.len, row, || len.map(F::from))?);
+                }
+
+                Ok(len_cell.unwrap())
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use halo2_proofs::{
+        circuit::SimpleFloorPlanner,
+        pasta::Fp,
+        plonk::{Circuit, Instance},
+    };
+    use crate::dev_tools::{min_k, mock_prove};
+
+    #[derive(Debug, Clone)]
+    struct MyConfig {
+        input: Column<Advice>,
+        output: Column<Instance>,
+        bit_length: BitLengthConfig<Fp, 4>,
+    }
+
+    // exposes the bit length of every value
+    #[derive(Default)]
+    struct MyCircuit {
+        values: Vec<u64>,
+    }
+
+    impl Circuit<Fp> for MyCircuit {
+        type Config = MyConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            let advice = [
+                meta.advice_column(),
+                meta.advice_column(),
+                meta.advice_column(),
+                meta.advice_column(),
+                meta.advice_column(),
+                meta.advice_column(),
+            ];
+            let output = meta.instance_column();
+            meta.enable_equality(output);
+
+            MyConfig {
+                input: advice[0],
+                output,
+                bit_length: BitLengthChip::configure(meta, advice),
+            }
+        }
+
+        fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<Fp>) -> Result<(), Error> {
+            let chip = BitLengthChip::construct(config.bit_length);
+            chip.load_table(&mut layouter)?;
+
+            for (row, value) in self.values.iter().enumerate() {
+                let value = layouter.assign_region(
+                    || "load value",
+                    |mut region| region.assign_advice(|| "value", config.input, 0, || Value::known(Fp::from(*value))),
+                )?;
+                let len = chip.bit_length(layouter.namespace(|| "bit length"), &value)?;
+                layouter.constrain_instance(len.cell(), config.output, row)?;
+            }
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_bit_length() {
+        let values = vec![0, 1, 5, 255, 256, 0x0105ff, 1 << 31];
+        let lengths = [1, 1, 3, 8, 9, 17, 32].map(Fp::from).to_vec();
+        let circuit = MyCircuit { values };
+        mock_prove(&circuit, vec![lengths.clone()]).unwrap().assert_satisfied();
+        let k = min_k(&circuit, &[lengths.clone()]).unwrap();
+        crate::dev_tools::assert_fully_constrained(k, &circuit, vec![lengths]);
+
+        // 256 has 9 bits, not 8
+        let circuit = MyCircuit { values: vec![256] };
+        let prover = mock_prove(&circuit, vec![vec![Fp::from(8)]]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_bit_length_reference() {
+        use crate::dev_tools::check_output;
+
+        check_output(
+            10,
+            0,
+            8,
+            // every bit length from 1 to 32 is as likely
+            |rng| (rng.next_u64() >> 32) >> rng.below(32),
+            |value| BitLengthChip::<Fp, 4>::native(*value),
+            |rng, len| len + 1 + rng.below(16) as u64,
+            |value, len| (MyCircuit { values: vec![*value] }, vec![vec![Fp::from(*len)]]),
+        );
+    }
+
+    #[test]
+    fn test_bit_length_forged_witness() {
+        use crate::dev_tools::{Forgeries, Rejection};
+
+        // 256 = 0x00000100, the bytes go from the most significant one: rows 0 to 3 hold 0x00, 0x00, 0x01, 0x00
+        let forgeries = Forgeries::new(|| MyCircuit { values: vec![256] }, vec![vec![Fp::from(9)]]);
+
+        // byte_inv = 0 claims that 0x01 is zero, column 5 is byte_inv
+        forgeries
+            .forge("bit length", 5, 2, Fp::zero())
+            .assert_rejected(Rejection::Gate("is_zero"));
+
+        // 0x01 doesn't have 2 bits, column 2 is bits
+        forgeries
+            .forge("bit length", 2, 2, Fp::from(2))
+            .assert_rejected(Rejection::Lookup(1));
+
+        // 256 has 9 bits, not 8. Column 4 is len
+        forgeries
+            .forge("bit length", 4, 3, Fp::from(8))
+            .assert_rejected(Rejection::Gate("bit length step"));
+    }
+}
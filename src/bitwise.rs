@@ -0,0 +1,382 @@
+// Goal: bitwise operations (XOR, AND, OR, NOT) on assigned cells, that's what we need for hash-like computations.
+// These are not polynomial so we look them up in a table of (a, b, a op b) over BITS-bit limbs, one table per operation.
+// The tables live in a TableRegistry (see table_registry.rs), the chip registers them in `configure`
+// and whoever owns the registry loads it once. The operation of a row is picked by its selector.
+// Larger words (e.g. 32 or 64 bits) are decomposed into limbs, most significant limb first,
+// and a running sum proves that the limbs recompose the word:
+//   acc_0 = limb_0
+//   acc_i = acc_{i-1} * 2^BITS + limb_i
+// so that the accumulator of the last row is the word itself.
+//
+// e.g. 8-bit words with 4-bit limbs, XOR
+//   a   | b   | c   | acc_a | acc_b | acc_c | q_xor | q_acc_first | q_acc
+//   0x1 | 0x3 | 0x2 | 0x1   | 0x3   | 0x2   | 1     | 1           | 0
+//   0x5 | 0x9 | 0xc | 0x15  | 0x39  | 0x2c  | 1     | 0           | 1
+
+use std::marker::PhantomData;
+
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    circuit::{AssignedCell, Layouter, Value},
+    plonk::{Advice, Column, ConstraintSystem, Constraints, Error, Expression, Selector},
+    poly::Rotation,
+};
+
+use crate::table_registry::{LogicalTable, TableRegistry};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitwiseOp {
+    Xor,
+    And,
+    Or,
+    Not,
+}
+
+impl BitwiseOp {
+    // the registry table of the operation over `bits`-bit limbs
+    pub fn table(&self, bits: usize) -> LogicalTable {
+        match self {
+            BitwiseOp::Xor => LogicalTable::Xor { num_bits: bits },
+            BitwiseOp::And => LogicalTable::And { num_bits: bits },
+            BitwiseOp::Or => LogicalTable::Or { num_bits: bits },
+            BitwiseOp::Not => LogicalTable::Not { num_bits: bits },
+        }
+    }
+
+    // native version of the operation over `bits`-bit values
+    pub fn native(&self, a: u64, b: u64, bits: usize) -> u64 {
+        let mask = if bits == 64 { u64::MAX } else { (1 << bits) - 1 };
+        match self {
+            BitwiseOp::Xor => a ^ b,
+            BitwiseOp::And => a & b,
+            BitwiseOp::Or => a | b,
+            BitwiseOp::Not => !a & mask,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BitwiseConfig<F: FieldExt, const BITS: usize> {
+    a: Column<Advice>,
+    b: Column<Advice>,
+    c: Column<Advice>,
+    acc_a: Column<Advice>,
+    acc_b: Column<Advice>,
+    acc_c: Column<Advice>,
+    q_xor: Selector,
+    q_and: Selector,
+    q_or: Selector,
+    q_not: Selector,
+    q_acc_first: Selector,
+    q_acc: Selector,
+    _marker: PhantomData<F>,
+}
+
+impl<F: FieldExt, const BITS: usize> BitwiseConfig<F, BITS> {
+    // the selector enabling the lookup of `op`
+    fn q_lookup(&self, op: BitwiseOp) -> Selector {
+        match op {
+            BitwiseOp::Xor => self.q_xor,
+            BitwiseOp::And => self.q_and,
+            BitwiseOp::Or => self.q_or,
+            BitwiseOp::Not => self.q_not,
+        }
+    }
+}
+
+pub struct BitwiseChip<F: FieldExt, const BITS: usize> {
+    config: BitwiseConfig<F, BITS>,
+}
+
+impl<F: FieldExt, const BITS: usize> BitwiseChip<F, BITS> {
+    pub fn construct(config: BitwiseConfig<F, BITS>) -> Self {
+        Self { config }
+    }
+
+    pub fn configure(meta: &mut ConstraintSystem<F>, registry: &mut TableRegistry<F>) -> BitwiseConfig<F, BITS> {
+        // 8-bit limbs already need 3 * 2^16 rows, anything larger is not practical
+        assert!(BITS <= 8);
+
+        let a = meta.advice_column();
+        let b = meta.advice_column();
+        let c = meta.advice_column();
+        let acc_a = meta.advice_column();
+        let acc_b = meta.advice_column();
+        let acc_c = meta.advice_column();
+        // inputs are copied into the accumulators and the output is read from there
+        for column in [acc_a, acc_b, acc_c] {
+            meta.enable_equality(column);
+        }
+
+        // Simple selectors cannot appear in lookup arguments.
+        // The selectors are fixed: if the operation was an advice column the prover could pick it!
+        let q_xor = meta.complex_selector();
+        let q_and = meta.complex_selector();
+        let q_or = meta.complex_selector();
+        let q_not = meta.complex_selector();
+        let q_acc_first = meta.selector();
+        let q_acc = meta.selector();
+
+        // every row of limbs must be a row of the table of its operation
+        for (op, q_lookup) in [(BitwiseOp::Xor, q_xor), (BitwiseOp::And, q_and), (BitwiseOp::Or, q_or), (BitwiseOp::Not, q_not)] {
+            let tag = registry.register(op.table(BITS));
+            registry.lookup(
+                meta,
+                tag,
+                |meta| meta.query_selector(q_lookup),
+                |meta| {
+                    vec![
+                        meta.query_advice(a, Rotation::cur()),
+                        meta.query_advice(b, Rotation::cur()),
+                        meta.query_advice(c, Rotation::cur()),
+                    ]
+                },
+            );
+        }
+
+        // the running sum starts from the most significant limb
+        meta.create_gate("bitwise acc first", |meta| {
+            let q_acc_first = meta.query_selector(q_acc_first);
+            let constraints = [(a, acc_a), (b, acc_b), (c, acc_c)].map(|(limb, acc)| {
+                meta.query_advice(acc, Rotation::cur()) - meta.query_advice(limb, Rotation::cur())
+            });
+            Constraints::with_selector(q_acc_first, constraints)
+        });
+
+        // acc_i = acc_{i-1} * 2^BITS + limb_i
+        meta.create_gate("bitwise acc", |meta| {
+            let q_acc = meta.query_selector(q_acc);
+            let shift = Expression::Constant(F::from(1u64 << BITS));
+            let constraints = [(a, acc_a), (b, acc_b), (c, acc_c)].map(|(limb, acc)| {
+                meta.query_advice(acc, Rotation::cur())
+                    - (meta.query_advice(acc, Rotation::prev()) * shift.clone()
+                        + meta.query_advice(limb, Rotation::cur()))
+            });
+            Constraints::with_selector(q_acc, constraints)
+        });
+
+        BitwiseConfig {
+            a,
+            b,
+            c,
+            acc_a,
+            acc_b,
+            acc_c,
+            q_xor,
+            q_and,
+            q_or,
+            q_not,
+            q_acc_first,
+            q_acc,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn xor(&self, layouter: impl Layouter<F>, a: &AssignedCell<F, F>, b: &AssignedCell<F, F>) -> Result<AssignedCell<F, F>, Error> {
+        self.assign_op(layouter, BitwiseOp::Xor, a, Some(b), BITS)
+    }
+
+    pub fn and(&self, layouter: impl Layouter<F>, a: &AssignedCell<F, F>, b: &AssignedCell<F, F>) -> Result<AssignedCell<F, F>, Error> {
+        self.assign_op(layouter, BitwiseOp::And, a, Some(b), BITS)
+    }
+
+    pub fn or(&self, layouter: impl Layouter<F>, a: &AssignedCell<F, F>, b: &AssignedCell<F, F>) -> Result<AssignedCell<F, F>, Error> {
+        self.assign_op(layouter, BitwiseOp::Or, a, Some(b), BITS)
+    }
+
+    pub fn not(&self, layouter: impl Layouter<F>, a: &AssignedCell<F, F>) -> Result<AssignedCell<F, F>, Error> {
+        self.assign_op(layouter, BitwiseOp::Not, a, None, BITS)
+    }
+
+    // Same operations over words of `word_bits` bits (e.g. 32 or 64), decomposed into BITS-bit limbs
+    pub fn xor_word(&self, layouter: impl Layouter<F>, a: &AssignedCell<F, F>, b: &AssignedCell<F, F>, word_bits: usize) -> Result<AssignedCell<F, F>, Error> {
+        self.assign_op(layouter, BitwiseOp::Xor, a, Some(b), word_bits)
+    }
+
+    pub fn and_word(&self, layouter: impl Layouter<F>, a: &AssignedCell<F, F>, b: &AssignedCell<F, F>, word_bits: usize) -> Result<AssignedCell<F, F>, Error> {
+        self.assign_op(layouter, BitwiseOp::And, a, Some(b), word_bits)
+    }
+
+    pub fn or_word(&self, layouter: impl Layouter<F>, a: &AssignedCell<F, F>, b: &AssignedCell<F, F>, word_bits: usize) -> Result<AssignedCell<F, F>, Error> {
+        self.assign_op(layouter, BitwiseOp::Or, a, Some(b), word_bits)
+    }
+
+    pub fn not_word(&self, layouter: impl Layouter<F>, a: &AssignedCell<F, F>, word_bits: usize) -> Result<AssignedCell<F, F>, Error> {
+        self.assign_op(layouter, BitwiseOp::Not, a, None, word_bits)
+    }
+
+    // Decompose the inputs into limbs, look up every row of limbs and recompose the output.
+    // `b` is None for NOT, in that case the b limbs are 0 (that's how NOT rows look like in the table)
+    fn assign_op(
+        &self,
+        mut layouter: impl Layouter<F>,
+        op: BitwiseOp,
+        a: &AssignedCell<F, F>,
+        b: Option<&AssignedCell<F, F>>,
+        word_bits: usize,
+    ) -> Result<AssignedCell<F, F>, Error> {
+        // no limbs would leave the output unassigned, more than 64 bits don't fit in the u64 witnesses
+        assert!(
+            word_bits > 0 && word_bits % BITS == 0 && word_bits <= 64,
+            "word_bits must be a non-zero multiple of {} and at most 64, got {}",
+            BITS,
+            word_bits
+        );
+        let num_limbs = word_bits / BITS;
+
+        let a_word = a.value().map(|a| a.get_lower_128() as u64);
+        let b_word = match b {
+            Some(b) => b.value().map(|b| b.get_lower_128() as u64),
+            None => Value::known(0),
+        };
+        let c_word = a_word.zip(b_word).map(|(a, b)| op.native(a, b, word_bits));
+
+        // limb i, counting from the most significant one, and the running sum up to that limb
+        let limb = |word: Value<u64>, i: usize| word.map(|word| (word >> (BITS * (num_limbs - 1 - i))) & ((1 << BITS) - 1));
+        let acc = |word: Value<u64>, i: usize| word.map(|word| word >> (BITS * (num_limbs - 1 - i)));
+
+        layouter.assign_region(
+            || format!("{:?} word", op),
+            |mut region| {
+                let mut accs = None;
+
+                for i in 0..num_limbs {
+                    self.config.q_lookup(op).enable(&mut region, i)?;
+                    if i == 0 {
+                        self.config.q_acc_first.enable(&mut region, i)?;
+                    } else {
+                        self.config.q_acc.enable(&mut region, i)?;
+                    }
+
+                    let to_field = |value: Value<u64>| value.map(|value| F::from(value));
+                    region.assign_advice(|| "a", self.config.a, i, || to_field(limb(a_word, i)))?;
+                    region.assign_advice(|| "b", self.config.b, i, || to_field(limb(b_word, i)))?;
+                    region.assign_advice(|| "c", self.config.c, i, || to_field(limb(c_word, i)))?;
+
+                    let acc_a = region.assign_advice(|| "acc a", self.config.acc_a, i, || to_field(acc(a_word, i)))?;
+                    let acc_b = region.assign_advice(|| "acc b", self.config.acc_b, i, || to_field(acc(b_word, i)))?;
+                    let acc_c = region.assign_advice(|| "acc c", self.config.acc_c, i, || to_field(acc(c_word, i)))?;
+                    accs = Some((acc_a, acc_b, acc_c));
+                }
+
+                // the last accumulators are the words themselves
+                let (acc_a, acc_b, acc_c) = accs.expect("word_bits > 0 was checked above");
+                region.constrain_equal(a.cell(), acc_a.cell())?;
+                // for NOT there's no b, its limbs are all forced to 0 by the table
+                if let Some(b) = b {
+                    region.constrain_equal(b.cell(), acc_b.cell())?;
+                }
+
+                Ok(acc_c)
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use halo2_proofs::{
+        circuit::SimpleFloorPlanner,
+        pasta::Fp,
+        plonk::{Circuit, Instance},
+    };
+    use crate::dev_tools::{min_k, mock_prove};
+
+    #[derive(Debug, Clone)]
+    struct MyConfig<F: FieldExt> {
+        input: Column<Advice>,
+        output: Column<Instance>,
+        bitwise: BitwiseConfig<F, 4>,
+        registry: TableRegistry<F>,
+    }
+
+    // computes !((a ^ b) & (a | b)) on 32-bit words with 4-bit limbs and exposes it as a public output
+    #[derive(Default)]
+    struct MyCircuit {
+        a: u64,
+        b: u64,
+    }
+
+    impl<F: FieldExt> Circuit<F> for MyCircuit {
+        type Config = MyConfig<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            let input = meta.advice_column();
+            meta.enable_equality(input);
+            let output = meta.instance_column();
+            meta.enable_equality(output);
+
+            let mut registry = TableRegistry::configure(meta);
+            let bitwise = BitwiseChip::configure(meta, &mut registry);
+
+            MyConfig {
+                input,
+                output,
+                bitwise,
+                registry,
+            }
+        }
+
+        fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<F>) -> Result<(), Error> {
+            let chip = BitwiseChip::construct(config.bitwise);
+
+            let (a, b) = layouter.assign_region(
+                || "load inputs",
+                |mut region| {
+                    let a = region.assign_advice(|| "a", config.input, 0, || Value::known(F::from(self.a)))?;
+                    let b = region.assign_advice(|| "b", config.input, 1, || Value::known(F::from(self.b)))?;
+                    Ok((a, b))
+                },
+            )?;
+
+            let xor = chip.xor_word(layouter.namespace(|| "a ^ b"), &a, &b, 32)?;
+            let or = chip.or_word(layouter.namespace(|| "a | b"), &a, &b, 32)?;
+            let and = chip.and_word(layouter.namespace(|| "xor & or"), &xor, &or, 32)?;
+            let not = chip.not_word(layouter.namespace(|| "!and"), &and, 32)?;
+
+            config.registry.load(&mut layouter)?;
+            layouter.constrain_instance(not.cell(), config.output, 0)
+        }
+    }
+
+    #[test]
+    fn test_bitwise() {
+        let (a, b) = (0xdeadbeef, 0x01234567);
+        let circuit = MyCircuit { a, b };
+
+        let expected = !((a ^ b) & (a | b)) & 0xffffffff;
+        mock_prove(&circuit, vec![vec![Fp::from(expected)]]).unwrap().assert_satisfied();
+        // 1 padding row + 16 nibble rows + 3 * 256 rows for XOR, AND, OR + 16 rows for NOT
+        let k = min_k(&circuit, &[vec![Fp::from(expected)]]).unwrap();
+        assert_eq!(k, 10);
+        crate::dev_tools::assert_fully_constrained(k, &circuit, vec![vec![Fp::from(expected)]]);
+
+        let prover = mock_prove(&circuit, vec![vec![Fp::from(expected ^ 1)]]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_bitwise_forged_witness() {
+        use crate::dev_tools::{Forgeries, Rejection};
+
+        let (a, b) = (0xdeadbeef, 0x01234567);
+        let expected = !((a ^ b) & (a | b)) & 0xffffffff;
+        let forgeries = Forgeries::new(|| MyCircuit { a, b }, vec![vec![Fp::from(expected)]]);
+
+        // 0xd ^ 0x0 is not 0, column 3 is c. Lookups 0 and 1 range check a and b, lookup 2 is the XOR table
+        forgeries
+            .forge("Xor word", 3, 0, Fp::zero())
+            .assert_rejected(Rejection::Lookup(2));
+
+        // a running sum that doesn't recompose the limbs, column 6 is acc c
+        forgeries
+            .forge("Xor word", 6, 7, Fp::from(a ^ b ^ 1))
+            .assert_rejected(Rejection::Gate("bitwise acc"));
+    }
+}
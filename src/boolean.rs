@@ -0,0 +1,402 @@
+// Goal: combine boolean assigned cells (e.g. the output of IsZeroChip or the a_equals_b flag of example3)
+// into compound predicates such as `a == b && c != d`.
+// Every operation has its own selector and its own gate. The gates also enforce the booleanity of their inputs,
+// if the inputs are boolean the output is boolean too.
+//
+//   l | r | out | q_and | q_or | q_xor | q_not | q_assert_true
+//   1 | 0 | 0   | 1     | 0    | 0     | 0     | 0
+//   1 | 0 | 1   | 0     | 1    | 0     | 0     | 0
+//   1 | 0 | 0   | 0     | 0    | 0     | 1     | 0
+//   1 |   |     | 0     | 0    | 0     | 0     | 1
+
+use std::marker::PhantomData;
+
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    circuit::{AssignedCell, Layouter, Value},
+    plonk::{Advice, Column, ConstraintSystem, Constraints, Error, Expression, Selector},
+    poly::Rotation,
+};
+
+#[derive(Debug, Clone)]
+pub struct BooleanConfig {
+    l: Column<Advice>,
+    r: Column<Advice>,
+    out: Column<Advice>,
+    q_and: Selector,
+    q_or: Selector,
+    q_xor: Selector,
+    q_not: Selector,
+    q_assert_true: Selector,
+}
+
+pub struct BooleanChip<F: FieldExt> {
+    config: BooleanConfig,
+    _marker: PhantomData<F>,
+}
+
+impl<F: FieldExt> BooleanChip<F> {
+    pub fn construct(config: BooleanConfig) -> Self {
+        Self {
+            config,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn configure(
+        meta: &mut ConstraintSystem<F>,
+        l: Column<Advice>,
+        r: Column<Advice>,
+        out: Column<Advice>,
+    ) -> BooleanConfig {
+        meta.enable_equality(l);
+        meta.enable_equality(r);
+        meta.enable_equality(out);
+
+        let q_and = meta.selector();
+        let q_or = meta.selector();
+        let q_xor = meta.selector();
+        let q_not = meta.selector();
+        let q_assert_true = meta.selector();
+
+        let one = || Expression::Constant(F::one());
+        // b * (1 - b) = 0 only if b is 0 or 1
+        let bool_check = |b: Expression<F>| b.clone() * (one() - b);
+
+        // l AND r = l * r
+        meta.create_gate("and", |meta| {
+            let q_and = meta.query_selector(q_and);
+            let l = meta.query_advice(l, Rotation::cur());
+            let r = meta.query_advice(r, Rotation::cur());
+            let out = meta.query_advice(out, Rotation::cur());
+            Constraints::with_selector(
+                q_and,
+                [
+                    ("l is bool", bool_check(l.clone())),
+                    ("r is bool", bool_check(r.clone())),
+                    ("l and r", out - l * r),
+                ],
+            )
+        });
+
+        // l OR r = l + r - l * r
+        meta.create_gate("or", |meta| {
+            let q_or = meta.query_selector(q_or);
+            let l = meta.query_advice(l, Rotation::cur());
+            let r = meta.query_advice(r, Rotation::cur());
+            let out = meta.query_advice(out, Rotation::cur());
+            Constraints::with_selector(
+                q_or,
+                [
+                    ("l is bool", bool_check(l.clone())),
+                    ("r is bool", bool_check(r.clone())),
+                    ("l or r", out - (l.clone() + r.clone() - l * r)),
+                ],
+            )
+        });
+
+        // l XOR r = l + r - 2 * l * r
+        meta.create_gate("xor", |meta| {
+            let q_xor = meta.query_selector(q_xor);
+            let l = meta.query_advice(l, Rotation::cur());
+            let r = meta.query_advice(r, Rotation::cur());
+            let out = meta.query_advice(out, Rotation::cur());
+            Constraints::with_selector(
+                q_xor,
+                [
+                    ("l is bool", bool_check(l.clone())),
+                    ("r is bool", bool_check(r.clone())),
+                    (
+                        "l xor r",
+                        out - (l.clone() + r.clone() - Expression::Constant(F::from(2)) * l * r),
+                    ),
+                ],
+            )
+        });
+
+        // NOT l = 1 - l
+        meta.create_gate("not", |meta| {
+            let q_not = meta.query_selector(q_not);
+            let l = meta.query_advice(l, Rotation::cur());
+            let out = meta.query_advice(out, Rotation::cur());
+            Constraints::with_selector(
+                q_not,
+                [("l is bool", bool_check(l.clone())), ("not l", out - (one() - l))],
+            )
+        });
+
+        // l = 1, booleanity comes for free
+        meta.create_gate("assert true", |meta| {
+            let q_assert_true = meta.query_selector(q_assert_true);
+            let l = meta.query_advice(l, Rotation::cur());
+            Constraints::with_selector(q_assert_true, [("l is true", l - one())])
+        });
+
+        BooleanConfig {
+            l,
+            r,
+            out,
+            q_and,
+            q_or,
+            q_xor,
+            q_not,
+            q_assert_true,
+        }
+    }
+
+    // Native references of the operations, over bools
+    pub fn native_and(l: bool, r: bool) -> bool {
+        l && r
+    }
+
+    pub fn native_or(l: bool, r: bool) -> bool {
+        l || r
+    }
+
+    pub fn native_xor(l: bool, r: bool) -> bool {
+        l != r
+    }
+
+    pub fn native_not(l: bool) -> bool {
+        !l
+    }
+
+    pub fn and(&self, layouter: impl Layouter<F>, l: &AssignedCell<F, F>, r: &AssignedCell<F, F>) -> Result<AssignedCell<F, F>, Error> {
+        self.binary_op(layouter, "and", self.config.q_and, l, r, |l, r| l * r)
+    }
+
+    pub fn or(&self, layouter: impl Layouter<F>, l: &AssignedCell<F, F>, r: &AssignedCell<F, F>) -> Result<AssignedCell<F, F>, Error> {
+        self.binary_op(layouter, "or", self.config.q_or, l, r, |l, r| l + r - l * r)
+    }
+
+    pub fn xor(&self, layouter: impl Layouter<F>, l: &AssignedCell<F, F>, r: &AssignedCell<F, F>) -> Result<AssignedCell<F, F>, Error> {
+        self.binary_op(layouter, "xor", self.config.q_xor, l, r, |l, r| l + r - F::from(2) * l * r)
+    }
+
+    pub fn not(&self, mut layouter: impl Layouter<F>, l: &AssignedCell<F, F>) -> Result<AssignedCell<F, F>, Error> {
+        layouter.assign_region(
+            || "not",
+            |mut region| {
+                self.config.q_not.enable(&mut region, 0)?;
+                l.copy_advice(|| "l", &mut region, self.config.l, 0)?;
+                let out = l.value().map(|l| F::one() - l);
+                region.assign_advice(|| "out", self.config.out, 0, || out)
+            },
+        )
+    }
+
+    pub fn assert_true(&self, mut layouter: impl Layouter<F>, l: &AssignedCell<F, F>) -> Result<(), Error> {
+        layouter.assign_region(
+            || "assert true",
+            |mut region| {
+                self.config.q_assert_true.enable(&mut region, 0)?;
+                l.copy_advice(|| "l", &mut region, self.config.l, 0)?;
+                Ok(())
+            },
+        )
+    }
+
+    fn binary_op(
+        &self,
+        mut layouter: impl Layouter<F>,
+        name: &str,
+        selector: Selector,
+        l: &AssignedCell<F, F>,
+        r: &AssignedCell<F, F>,
+        op: impl Fn(F, F) -> F,
+    ) -> Result<AssignedCell<F, F>, Error> {
+        layouter.assign_region(
+            || name,
+            |mut region| {
+                selector.enable(&mut region, 0)?;
+                l.copy_advice(|| "l", &mut region, self.config.l, 0)?;
+                r.copy_advice(|| "r", &mut region, self.config.r, 0)?;
+                let out: Value<F> = l.value().zip(r.value()).map(|(l, r)| op(*l, *r));
+                region.assign_advice(|| "out", self.config.out, 0, || out)
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::is_zero::{IsZeroChip, IsZeroConfig, IsZeroInstructions};
+    use halo2_proofs::{circuit::SimpleFloorPlanner, dev::MockProver, pasta::Fp, plonk::Circuit};
+
+    #[derive(Debug, Clone)]
+    struct MyConfig<F: FieldExt> {
+        a: Column<Advice>,
+        b: Column<Advice>,
+        // materializes the is_zero expression into a cell so that it can be combined
+        is_zero_out: Column<Advice>,
+        q_is_zero: Selector,
+        is_zero: IsZeroConfig<F>,
+        boolean: BooleanConfig,
+    }
+
+    // proves that `a == b && !(c == d)` using IsZeroChip for the comparisons
+    #[derive(Default)]
+    struct MyCircuit<F> {
+        a: F,
+        b: F,
+        c: F,
+        d: F,
+    }
+
+    impl<F: FieldExt> Circuit<F> for MyCircuit<F> {
+        type Config = MyConfig<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            let a = meta.advice_column();
+            let b = meta.advice_column();
+            let is_zero_out = meta.advice_column();
+            let value_inv = meta.advice_column();
+            let q_is_zero = meta.selector();
+
+            let is_zero = IsZeroChip::configure(
+                meta,
+                |meta| meta.query_selector(q_is_zero),
+                |meta| meta.query_advice(a, Rotation::cur()) - meta.query_advice(b, Rotation::cur()),
+                value_inv,
+            );
+
+            meta.create_gate("is_zero out", |meta| {
+                let q_is_zero = meta.query_selector(q_is_zero);
+                let is_zero_out = meta.query_advice(is_zero_out, Rotation::cur());
+                vec![q_is_zero * (is_zero_out - is_zero.expr())]
+            });
+
+            let boolean = BooleanChip::configure(meta, a, b, is_zero_out);
+
+            MyConfig {
+                a,
+                b,
+                is_zero_out,
+                q_is_zero,
+                is_zero,
+                boolean,
+            }
+        }
+
+        fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<F>) -> Result<(), Error> {
+            let is_zero_chip = IsZeroChip::construct(config.is_zero.clone());
+            let boolean_chip = BooleanChip::construct(config.boolean.clone());
+
+            let mut equals = |name: &'static str, a: F, b: F| {
+                layouter.assign_region(
+                    || name,
+                    |mut region| {
+                        config.q_is_zero.enable(&mut region, 0)?;
+                        region.assign_advice(|| "a", config.a, 0, || Value::known(a))?;
+                        region.assign_advice(|| "b", config.b, 0, || Value::known(b))?;
+                        is_zero_chip.assign(&mut region, 0, Value::known(a - b))?;
+                        let out = if a == b { F::one() } else { F::zero() };
+                        region.assign_advice(|| "a == b", config.is_zero_out, 0, || Value::known(out))
+                    },
+                )
+            };
+
+            let a_equals_b = equals("a == b", self.a, self.b)?;
+            let c_equals_d = equals("c == d", self.c, self.d)?;
+
+            let c_not_equals_d = boolean_chip.not(layouter.namespace(|| "c != d"), &c_equals_d)?;
+            let predicate = boolean_chip.and(layouter.namespace(|| "a == b && c != d"), &a_equals_b, &c_not_equals_d)?;
+            // (p || p) ^ p is always false, so !((p || p) ^ p) must be true
+            let or = boolean_chip.or(layouter.namespace(|| "or"), &predicate, &predicate)?;
+            let xor = boolean_chip.xor(layouter.namespace(|| "xor"), &or, &predicate)?;
+            let not_xor = boolean_chip.not(layouter.namespace(|| "not xor"), &xor)?;
+
+            boolean_chip.assert_true(layouter.namespace(|| "not xor is true"), &not_xor)?;
+            boolean_chip.assert_true(layouter.namespace(|| "predicate is true"), &predicate)
+        }
+    }
+
+    #[test]
+    fn test_boolean() {
+        let k = 5;
+
+        let circuit = MyCircuit {
+            a: Fp::from(10),
+            b: Fp::from(10),
+            c: Fp::from(3),
+            d: Fp::from(4),
+        };
+        let prover = MockProver::run(k, &circuit, vec![]).unwrap();
+        prover.assert_satisfied();
+        crate::dev_tools::assert_fully_constrained(k, &circuit, vec![]);
+
+        // c == d so the predicate is false
+        let circuit = MyCircuit {
+            a: Fp::from(10),
+            b: Fp::from(10),
+            c: Fp::from(4),
+            d: Fp::from(4),
+        };
+        let prover = MockProver::run(k, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_boolean_reference() {
+        use crate::dev_tools::check_acceptance;
+
+        type Chip = BooleanChip<Fp>;
+        check_acceptance(
+            5,
+            0,
+            16,
+            // a == b and c == d half of the time each
+            |rng| {
+                let (a, c): (Fp, Fp) = (rng.field(), rng.field());
+                let b = if rng.below(2) == 0 { a } else { rng.field() };
+                let d = if rng.below(2) == 0 { c } else { rng.field() };
+                (a, b, c, d)
+            },
+            // the same operations as the circuit, both assertions must hold
+            |&(a, b, c, d)| {
+                let predicate = Chip::native_and(a == b, Chip::native_not(c == d));
+                let or = Chip::native_or(predicate, predicate);
+                Chip::native_not(Chip::native_xor(or, predicate)) && predicate
+            },
+            |&(a, b, c, d)| (MyCircuit { a, b, c, d }, vec![]),
+        );
+    }
+
+    #[test]
+    fn test_boolean_forged_witness() {
+        use crate::dev_tools::{Forgeries, Rejection};
+
+        let circuit = || MyCircuit {
+            a: Fp::from(10),
+            b: Fp::from(10),
+            c: Fp::from(3),
+            d: Fp::from(4),
+        };
+        let forgeries = Forgeries::new(circuit, vec![]);
+
+        // value_inv = 0 claims that c == d, column 3 is value_inv
+        forgeries
+            .forge("c == d", 3, 0, Fp::zero())
+            .assert_rejected(Rejection::Gate("is_zero"));
+
+        // a == b materialized as false, column 2 is out
+        forgeries
+            .forge("a == b", 2, 0, Fp::zero())
+            .assert_rejected(Rejection::Gate("is_zero out"));
+
+        // wrong outputs of the boolean operations
+        forgeries
+            .forge("and", 2, 0, Fp::from(2))
+            .assert_rejected(Rejection::Gate("and"));
+        // !(c == d) is 1, the first not claims it's 0
+        forgeries
+            .forge_nth("not", 0, 2, 0, Fp::zero())
+            .assert_rejected(Rejection::Gate("not"));
+    }
+}
@@ -0,0 +1,273 @@
+// Goal: split a field element into NUM_BYTES little-endian bytes inside the circuit, and pack bytes back into a field element.
+// Every byte is range checked using the 256-entry lookup table built in example5
+// and a linear-combination gate proves the recomposition:
+//   value = byte_0 + 256 * byte_1 + 256^2 * byte_2 + ...
+//
+//   value | byte   | q_decompose | q_lookup
+//   v     | byte_0 | 1           | 1
+//         | byte_1 | 0           | 1
+//         | ...    | 0           | 1
+//
+// Both directions use the same layout: when decomposing we witness the bytes, when recomposing we copy them in.
+
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    circuit::{AssignedCell, Layouter, Value},
+    plonk::{Advice, Column, ConstraintSystem, Constraints, Error, Expression, Selector},
+    poly::Rotation,
+};
+
+use crate::range_check::example5::table::RangeCheckTable;
+
+#[derive(Debug, Clone)]
+pub struct ByteDecompositionConfig<F: FieldExt, const NUM_BYTES: usize> {
+    value: Column<Advice>,
+    byte: Column<Advice>,
+    q_decompose: Selector,
+    q_lookup: Selector,
+    table: RangeCheckTable<F, 256>,
+}
+
+pub struct ByteDecompositionChip<F: FieldExt, const NUM_BYTES: usize> {
+    config: ByteDecompositionConfig<F, NUM_BYTES>,
+}
+
+impl<F: FieldExt, const NUM_BYTES: usize> ByteDecompositionChip<F, NUM_BYTES> {
+    pub fn construct(config: ByteDecompositionConfig<F, NUM_BYTES>) -> Self {
+        Self { config }
+    }
+
+    pub fn configure(
+        meta: &mut ConstraintSystem<F>,
+        value: Column<Advice>,
+        byte: Column<Advice>,
+    ) -> ByteDecompositionConfig<F, NUM_BYTES> {
+        // the bytes are computed natively from the lower 128 bits of the value,
+        // this also makes sure that 256^NUM_BYTES doesn't wrap around the modulus
+        assert!(NUM_BYTES <= 16);
+
+        meta.enable_equality(value);
+        meta.enable_equality(byte);
+
+        let q_decompose = meta.selector();
+        // Simple selectors cannot appear in lookup arguments
+        let q_lookup = meta.complex_selector();
+        let table = RangeCheckTable::configure(meta);
+
+        meta.create_gate("byte recomposition", |meta| {
+            let q_decompose = meta.query_selector(q_decompose);
+            let value = meta.query_advice(value, Rotation::cur());
+
+            // byte_0 + 256 * byte_1 + 256^2 * byte_2 + ...
+            let bytes = (0..NUM_BYTES).rev().fold(Expression::Constant(F::zero()), |acc, i| {
+                acc * Expression::Constant(F::from(256)) + meta.query_advice(byte, Rotation(i as i32))
+            });
+
+            Constraints::with_selector(q_decompose, [("byte recomposition", value - bytes)])
+        });
+
+        meta.lookup(|meta| {
+            let q_lookup = meta.query_selector(q_lookup);
+            let byte = meta.query_advice(byte, Rotation::cur());
+            vec![(q_lookup * byte, table.value)]
+        });
+
+        ByteDecompositionConfig {
+            value,
+            byte,
+            q_decompose,
+            q_lookup,
+            table,
+        }
+    }
+
+    // Native reference: the little-endian bytes of value, None if it doesn't fit in NUM_BYTES bytes
+    pub fn native(value: F) -> Option<Vec<u8>> {
+        let lo = value.get_lower_128();
+        let fits = F::from_u128(lo) == value && lo.checked_shr(8 * NUM_BYTES as u32).unwrap_or(0) == 0;
+        fits.then(|| (0..NUM_BYTES).map(|i| (lo >> (8 * i)) as u8).collect())
+    }
+
+    // The range check table must be loaded once per circuit
+    pub fn load_table(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
+        self.config.table.load(layouter)
+    }
+
+    // Split `value` into NUM_BYTES little-endian bytes
+    pub fn decompose(
+        &self,
+        mut layouter: impl Layouter<F>,
+        value: &AssignedCell<F, F>,
+    ) -> Result<Vec<AssignedCell<F, F>>, Error> {
+        let value_u128 = value.value().map(|value| value.get_lower_128());
+
+        layouter.assign_region(
+            || "byte decomposition",
+            |mut region| {
+                self.config.q_decompose.enable(&mut region, 0)?;
+                value.copy_advice(|| "value", &mut region, self.config.value, 0)?;
+
+                (0..NUM_BYTES)
+                    .map(|i| {
+                        self.config.q_lookup.enable(&mut region, i)?;
+                        let byte = value_u128.map(|value| F::from(((value >> (8 * i)) & 0xff) as u64));
+                        region.assign_advice(|| format!("byte {}", i), self.config.byte, i, || byte)
+                    })
+                    .collect()
+            },
+        )
+    }
+
+    // Pack little-endian `bytes` back into a single field element. The bytes are range checked again
+    pub fn recompose(
+        &self,
+        mut layouter: impl Layouter<F>,
+        bytes: &[AssignedCell<F, F>],
+    ) -> Result<AssignedCell<F, F>, Error> {
+        assert_eq!(bytes.len(), NUM_BYTES);
+
+        let value = bytes.iter().rev().fold(Value::known(F::zero()), |acc, byte| {
+            acc.zip(byte.value()).map(|(acc, byte)| acc * F::from(256) + byte)
+        });
+
+        layouter.assign_region(
+            || "byte recomposition",
+            |mut region| {
+                self.config.q_decompose.enable(&mut region, 0)?;
+                for (i, byte) in bytes.iter().enumerate() {
+                    self.config.q_lookup.enable(&mut region, i)?;
+                    byte.copy_advice(|| format!("byte {}", i), &mut region, self.config.byte, i)?;
+                }
+                region.assign_advice(|| "value", self.config.value, 0, || value)
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use halo2_proofs::{
+        circuit::SimpleFloorPlanner,
+        pasta::Fp,
+        plonk::{Circuit, Instance},
+    };
+
+    use crate::dev_tools::{min_k, mock_prove};
+
+    #[derive(Debug, Clone)]
+    struct MyConfig<F: FieldExt> {
+        value: Column<Advice>,
+        output: Column<Instance>,
+        bytes: ByteDecompositionConfig<F, 4>,
+    }
+
+    // decomposes a value into 4 bytes, exposes the least significant one
+    // and exposes the value recomposed from the bytes
+    #[derive(Default)]
+    struct MyCircuit<F> {
+        value: Value<F>,
+    }
+
+    impl<F: FieldExt> Circuit<F> for MyCircuit<F> {
+        type Config = MyConfig<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            let value = meta.advice_column();
+            let byte = meta.advice_column();
+            let output = meta.instance_column();
+            meta.enable_equality(output);
+
+            MyConfig {
+                value,
+                output,
+                bytes: ByteDecompositionChip::configure(meta, value, byte),
+            }
+        }
+
+        fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<F>) -> Result<(), Error> {
+            let chip = ByteDecompositionChip::construct(config.bytes);
+
+            let value = layouter.assign_region(
+                || "load value",
+                |mut region| region.assign_advice(|| "value", config.value, 0, || self.value),
+            )?;
+
+            let bytes = chip.decompose(layouter.namespace(|| "decompose"), &value)?;
+            let recomposed = chip.recompose(layouter.namespace(|| "recompose"), &bytes)?;
+            chip.load_table(&mut layouter)?;
+
+            layouter.constrain_instance(bytes[0].cell(), config.output, 0)?;
+            layouter.constrain_instance(recomposed.cell(), config.output, 1)
+        }
+    }
+
+    #[test]
+    fn test_byte_decomposition() {
+        let circuit = MyCircuit {
+            value: Value::known(Fp::from(0x12345678)),
+        };
+        let instances = vec![vec![Fp::from(0x78), Fp::from(0x12345678)]];
+        mock_prove(&circuit, instances.clone()).unwrap().assert_satisfied();
+        let k = min_k(&circuit, &instances).unwrap();
+        crate::dev_tools::assert_fully_constrained(k, &circuit, instances);
+
+        // a value that does not fit into 4 bytes can't be decomposed
+        let circuit = MyCircuit {
+            value: Value::known(Fp::from(0x1_0000_0000)),
+        };
+        let prover = mock_prove(&circuit, vec![vec![Fp::zero(), Fp::zero()]]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_byte_decomposition_reference() {
+        use crate::dev_tools::check_output;
+
+        type Chip = ByteDecompositionChip<Fp, 4>;
+        check_output(
+            9,
+            0,
+            8,
+            |rng| Fp::from(rng.next_u64() >> 32),
+            // the least significant byte and the recomposed value
+            |value| (Fp::from(Chip::native(*value).unwrap()[0] as u64), *value),
+            |rng, &(byte, value)| (byte + Fp::from(1 + rng.below(16) as u64), value),
+            |value, &(byte, recomposed)| {
+                let circuit = MyCircuit {
+                    value: Value::known(*value),
+                };
+                (circuit, vec![vec![byte, recomposed]])
+            },
+        );
+        assert_eq!(Chip::native(Fp::from(0x12345678)), Some(vec![0x78, 0x56, 0x34, 0x12]));
+        assert_eq!(Chip::native(Fp::from(1 << 32)), None);
+        assert_eq!(Chip::native(-Fp::one()), None);
+    }
+
+    #[test]
+    fn test_byte_decomposition_forged_witness() {
+        use crate::dev_tools::{Forgeries, Rejection};
+
+        let circuit = || MyCircuit {
+            value: Value::known(Fp::from(0x12345678)),
+        };
+        let forgeries = Forgeries::new(circuit, vec![vec![Fp::from(0x78), Fp::from(0x12345678)]]);
+
+        // 0x178 + 256 * 0x55 still recomposes 0x5678 but 0x178 is not a byte, column 1 is byte
+        forgeries
+            .forge("byte decomposition", 1, 0, Fp::from(0x178))
+            .forge("byte decomposition", 1, 1, Fp::from(0x55))
+            .assert_rejected(Rejection::Lookup(0));
+
+        // bytes that don't recompose the value
+        forgeries
+            .forge("byte decomposition", 1, 0, Fp::from(0x79))
+            .assert_rejected(Rejection::Gate("byte recomposition"));
+    }
+}
@@ -0,0 +1,52 @@
+// Tools to inspect the circuits of this crate beyond what MockProver reports.
+// The analyzers synthesize a circuit with its own floor planner into a Recorder, which keeps every assignment
+// (advice and fixed values, enabled selectors, copy constraints) next to the ConstraintSystem of the circuit.
+
+mod recorder;
+// seeded random generator of the fuzzer and the property tests
+mod rng;
+pub use rng::XorShift;
+
+// lists the advice cells that are assigned but not constrained by anything
+pub mod analyzer;
+pub use analyzer::{assert_fully_constrained, unconstrained_cells, UnconstrainedCell};
+
+// overrides chosen advice cells after honest synthesis, to check that MockProver rejects forged witnesses
+pub mod tamper;
+pub use tamper::{Forgeries, Rejection, Tampered};
+
+// forges random advice cells with a seeded generator and reports the mutations that still verify
+pub mod fuzzer;
+pub use fuzzer::{fuzz, FuzzOptions, FuzzReport, Mutation};
+
+// checks chips against their native reference on seeded random inputs
+pub mod property;
+pub use property::{check_acceptance, check_output};
+
+// maps the failures of MockProver back to regions, cells and chip-level messages
+pub mod diagnostics;
+pub use diagnostics::{diagnose, format_value, Diagnostic, Failure, FailureKind};
+
+// prints the gates, lookups and selectors of a ConstraintSystem with column names, or exports them as JSON
+pub mod describe;
+pub use describe::{describe, CircuitDescription};
+
+// finds the smallest k of a circuit and reports its columns, degree, rows and estimated proof size
+pub mod cost;
+pub use cost::{cost_report, CostReport};
+
+// picks the smallest k for MockProver and for a real proof, instead of a hard-coded one
+pub mod prover;
+pub use prover::{min_k, mock_prove, prove_and_verify};
+
+// draws the chips, regions and gates of a circuit with its copies and lookups as a Graphviz graph
+#[cfg(feature = "dev-graph")]
+pub mod graph;
+#[cfg(feature = "dev-graph")]
+pub use graph::{circuit_graph, CircuitGraph};
+
+// renders the layout of a circuit into a PNG or an SVG file, sized from its columns and rows
+#[cfg(feature = "dev-graph")]
+pub mod layout;
+#[cfg(feature = "dev-graph")]
+pub use layout::{layout_path, render_layout, render_layout_with, LayoutFormat, LayoutOptions};
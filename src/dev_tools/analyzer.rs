@@ -0,0 +1,190 @@
+// Goal: find advice cells that are assigned but constrained by nothing.
+// MockProver only reports violated constraints, so a cell that no gate, lookup or copy constraint looks at
+// (e.g. num_bits assigned on a path where no gate queries it) goes unnoticed: any value would verify.
+//
+// For every row we walk the polynomials of every gate and the inputs of every lookup and keep the advice cells
+// of the terms that are not trivially zero on that row. A term is trivially zero when it is multiplied
+// by a disabled selector, a zero fixed cell or a zero constant, that's how `q * (a - b)` leaves a and b alone
+// on the rows where q is disabled. The advice cells of copy constraints are constrained too.
+// This is a structural check: a cell that is only multiplied by an advice value that happens to be zero
+// still counts as constrained.
+
+use std::collections::HashSet;
+use std::fmt;
+
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    plonk::{Any, Circuit, Error, Expression},
+};
+
+use super::recorder::Recorder;
+
+// An advice cell that can take any value without the circuit noticing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnconstrainedCell {
+    pub region: String,
+    pub annotation: String,
+    pub column: usize,
+    pub row: usize,
+}
+
+impl fmt::Display for UnconstrainedCell {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "'{}' in region '{}' (advice column {}, row {})",
+            self.annotation, self.region, self.column, self.row
+        )
+    }
+}
+
+// The advice cells (column, row) that the expression depends on at `row`, or None if it's trivially zero there
+fn active_cells<F: FieldExt>(expr: &Expression<F>, row: usize, recorder: &Recorder<F>) -> Option<Vec<(usize, usize)>> {
+    let n = recorder.n() as i32;
+    let at = |rotation: halo2_proofs::poly::Rotation| (row as i32 + rotation.0).rem_euclid(n) as usize;
+
+    expr.evaluate(
+        &|constant| if constant == F::zero() { None } else { Some(vec![]) },
+        &|selector| recorder.selectors.contains(&(selector, row)).then(Vec::new),
+        &|_, column, rotation| (recorder.fixed_value(column, at(rotation)) != F::zero()).then(Vec::new),
+        &|_, column, rotation| Some(vec![(column, at(rotation))]),
+        &|_, _, _| Some(vec![]),
+        &|a| a,
+        &|a, b| match (a, b) {
+            (Some(mut a), Some(b)) => {
+                a.extend(b);
+                Some(a)
+            }
+            (a, None) => a,
+            (None, b) => b,
+        },
+        &|a, b| match (a, b) {
+            (Some(mut a), Some(b)) => {
+                a.extend(b);
+                Some(a)
+            }
+            _ => None,
+        },
+        &|a, scalar| if scalar == F::zero() { None } else { a },
+    )
+}
+
+// Synthesize `circuit` and list the assigned advice cells that appear in no active gate, lookup or copy constraint
+pub fn unconstrained_cells<F: FieldExt, C: Circuit<F>>(
+    k: u32,
+    circuit: &C,
+    instances: Vec<Vec<F>>,
+) -> Result<Vec<UnconstrainedCell>, Error> {
+    let (recorder, cs) = Recorder::synthesize(k, circuit, instances)?;
+
+    let mut constrained = HashSet::new();
+    for row in 0..recorder.usable_rows {
+        let gates = cs.gates().iter().flat_map(|gate| gate.polynomials().iter());
+        let lookups = cs.lookups().iter().flat_map(|lookup| lookup.input_expressions().iter());
+        for expr in gates.chain(lookups) {
+            if let Some(cells) = active_cells(expr, row, &recorder) {
+                constrained.extend(cells);
+            }
+        }
+    }
+
+    for (left, right) in &recorder.copies {
+        for (column, row) in [left, right] {
+            if *column.column_type() == Any::Advice {
+                constrained.insert((column.index(), *row));
+            }
+        }
+    }
+
+    Ok(recorder
+        .advice
+        .iter()
+        .filter(|(cell, _)| !constrained.contains(cell))
+        .map(|((column, row), advice)| UnconstrainedCell {
+            region: recorder.region_name(advice.region).to_string(),
+            annotation: advice.annotation.clone(),
+            column: *column,
+            row: *row,
+        })
+        .collect())
+}
+
+// Test helper: panics with the list of under-constrained cells, if any
+pub fn assert_fully_constrained<F: FieldExt, C: Circuit<F>>(k: u32, circuit: &C, instances: Vec<Vec<F>>) {
+    let cells = unconstrained_cells(k, circuit, instances).unwrap();
+    if !cells.is_empty() {
+        let cells: Vec<String> = cells.iter().map(|cell| format!("  {}", cell)).collect();
+        panic!("under-constrained advice cells:\n{}", cells.join("\n"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        pasta::Fp,
+        plonk::{Advice, Column, ConstraintSystem, Selector},
+        poly::Rotation,
+    };
+
+    #[derive(Debug, Clone)]
+    struct MyConfig {
+        a: Column<Advice>,
+        b: Column<Advice>,
+        q: Selector,
+    }
+
+    // a = b on the first row, then a is assigned again on a row where the gate is disabled
+    #[derive(Default)]
+    struct MyCircuit;
+
+    impl Circuit<Fp> for MyCircuit {
+        type Config = MyConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            let a = meta.advice_column();
+            let b = meta.advice_column();
+            let q = meta.selector();
+            meta.create_gate("a = b", |meta| {
+                let q = meta.query_selector(q);
+                let a = meta.query_advice(a, Rotation::cur());
+                let b = meta.query_advice(b, Rotation::cur());
+                vec![q * (a - b)]
+            });
+            MyConfig { a, b, q }
+        }
+
+        fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<Fp>) -> Result<(), Error> {
+            layouter.assign_region(
+                || "a = b",
+                |mut region| {
+                    config.q.enable(&mut region, 0)?;
+                    region.assign_advice(|| "a", config.a, 0, || Value::known(Fp::one()))?;
+                    region.assign_advice(|| "b", config.b, 0, || Value::known(Fp::one()))?;
+                    region.assign_advice(|| "dangling a", config.a, 1, || Value::known(Fp::one()))?;
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    #[test]
+    fn test_unconstrained_cells() {
+        let cells = unconstrained_cells(4, &MyCircuit, vec![]).unwrap();
+        assert_eq!(
+            cells,
+            vec![UnconstrainedCell {
+                region: "a = b".to_string(),
+                annotation: "dangling a".to_string(),
+                column: 0,
+                row: 1,
+            }]
+        );
+    }
+}
@@ -0,0 +1,214 @@
+// Goal: pick the parameters of a circuit instead of guessing them. The tests hard-code k = 4 or k = 9 and example5
+// explains its k = 9 by counting rows by hand: 256 rows of table + the rows reserved for the blinding factors > 2^8.
+// cost_report() finds the smallest k the circuit fits in and reports what the circuit costs at that k:
+//  - the columns of each type and the selectors (before halo2 compresses them into fixed columns)
+//  - the maximum degree of the gates, and of the whole constraint system (lookups and permutation included)
+//  - the number of lookups and of columns in the permutation
+//  - the used rows against the rows reserved for the blinding factors
+//  - the estimated size of a proof, with the cost model of halo2 (dev::CircuitCost) over the pasta curves
+//
+// The circuit is synthesized into a Recorder with its own floor planner for k = 1, 2... until every region,
+// table and constant fits in the usable rows, like MockProver would check it.
+
+use std::fmt;
+
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    dev::CircuitCost,
+    pasta::{Eq, Fp},
+    plonk::{Circuit, ConstraintSystem, Error},
+};
+
+use super::recorder::Recorder;
+
+// Beyond this, the recorder is too slow to be of any use (and the circuit probably too large for a test)
+pub const MAX_K: u32 = 20;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CostReport {
+    // the smallest k the circuit fits in
+    pub k: u32,
+    pub advice_columns: usize,
+    pub fixed_columns: usize,
+    pub instance_columns: usize,
+    pub selectors: usize,
+    pub max_gate_degree: usize,
+    // the degree of the constraint system, that's what the size of the extended domain depends on
+    pub degree: usize,
+    pub lookups: usize,
+    pub permutation_columns: usize,
+    // rows assigned by the regions, the tables and the constants
+    pub used_rows: usize,
+    // the blinding factors and the last row, no region can use them
+    pub blinding_rows: usize,
+    // estimated size of a proof with a single instance of the circuit, in bytes
+    pub proof_size: usize,
+}
+
+impl CostReport {
+    pub fn rows(&self) -> usize {
+        1 << self.k
+    }
+}
+
+impl fmt::Display for CostReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "k = {}: {} rows, {} used, {} reserved for blinding, {} free",
+            self.k,
+            self.rows(),
+            self.used_rows,
+            self.blinding_rows,
+            self.rows() - self.used_rows - self.blinding_rows
+        )?;
+        writeln!(
+            f,
+            "columns: {} advice, {} fixed, {} instance, {} selectors",
+            self.advice_columns, self.fixed_columns, self.instance_columns, self.selectors
+        )?;
+        writeln!(
+            f,
+            "degree: {} for the gates, {} for the constraint system",
+            self.max_gate_degree, self.degree
+        )?;
+        writeln!(
+            f,
+            "{} lookups, {} permutation columns",
+            self.lookups, self.permutation_columns
+        )?;
+        write!(f, "estimated proof size: {} bytes", self.proof_size)
+    }
+}
+
+// Synthesize `circuit` for the smallest k its regions, tables, constants and public inputs fit in
+pub(crate) fn synthesize_min_k<F: FieldExt, C: Circuit<F>>(
+    circuit: &C,
+    instances: &[Vec<F>],
+) -> Result<(Recorder<F>, ConstraintSystem<F>), Error> {
+    let instance_rows = instances.iter().map(Vec::len).max().unwrap_or(0);
+    for k in 1..=MAX_K {
+        match Recorder::synthesize(k, circuit, instances.to_vec()) {
+            Ok((recorder, cs)) if instance_rows <= recorder.usable_rows => return Ok((recorder, cs)),
+            Ok(_) | Err(Error::NotEnoughRowsAvailable { .. }) => continue,
+            Err(error) => return Err(error),
+        }
+    }
+    Err(Error::NotEnoughRowsAvailable { current_k: MAX_K })
+}
+
+// Find the smallest k for `circuit` and report its costs at that k
+pub fn cost_report<C: Circuit<Fp>>(circuit: &C, instances: &[Vec<Fp>]) -> Result<CostReport, Error> {
+    let (recorder, cs) = synthesize_min_k(circuit, instances)?;
+
+    let proof_size: usize = CircuitCost::<Eq, C>::measure(recorder.k as usize, circuit)
+        .proof_size(1)
+        .into();
+
+    Ok(CostReport {
+        k: recorder.k,
+        advice_columns: cs.num_advice_columns(),
+        fixed_columns: cs.num_fixed_columns(),
+        instance_columns: cs.num_instance_columns(),
+        selectors: cs.num_selectors(),
+        max_gate_degree: cs
+            .gates()
+            .iter()
+            .flat_map(|gate| gate.polynomials().iter())
+            .map(|polynomial| polynomial.degree())
+            .max()
+            .unwrap_or(0),
+        degree: cs.degree(),
+        lookups: cs.lookups().len(),
+        permutation_columns: cs.permutation().get_columns().len(),
+        used_rows: recorder.used_rows,
+        blinding_rows: recorder.n() - recorder.usable_rows,
+        proof_size,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        plonk::{Advice, Column, Instance, Selector},
+        poly::Rotation,
+    };
+
+    #[derive(Debug, Clone)]
+    struct MyConfig {
+        a: Column<Advice>,
+        instance: Column<Instance>,
+        q: Selector,
+    }
+
+    // a[i + 1] = a[i] * a[i] on `rows` rows, the last value is public
+    struct MyCircuit {
+        rows: usize,
+    }
+
+    impl Circuit<Fp> for MyCircuit {
+        type Config = MyConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self { rows: self.rows }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            let a = meta.advice_column();
+            let instance = meta.instance_column();
+            let q = meta.selector();
+            meta.enable_equality(a);
+            meta.enable_equality(instance);
+            meta.create_gate("square", |meta| {
+                let q = meta.query_selector(q);
+                let a_cur = meta.query_advice(a, Rotation::cur());
+                let a_next = meta.query_advice(a, Rotation::next());
+                vec![q * (a_next - a_cur.clone() * a_cur)]
+            });
+            MyConfig { a, instance, q }
+        }
+
+        fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<Fp>) -> Result<(), Error> {
+            let last = layouter.assign_region(
+                || "squares",
+                |mut region| {
+                    let mut a = region.assign_advice(|| "a", config.a, 0, || Value::known(Fp::from(2)))?;
+                    for row in 1..self.rows {
+                        config.q.enable(&mut region, row - 1)?;
+                        let value = a.value().map(|a| a * a);
+                        a = region.assign_advice(|| "a", config.a, row, || value)?;
+                    }
+                    Ok(a)
+                },
+            )?;
+            layouter.constrain_instance(last.cell(), config.instance, 0)
+        }
+    }
+
+    #[test]
+    fn test_cost_report() {
+        let report = cost_report(&MyCircuit { rows: 4 }, &[vec![Fp::from(256)]]).unwrap();
+        assert_eq!(
+            (report.advice_columns, report.fixed_columns, report.instance_columns, report.selectors),
+            (1, 0, 1, 1),
+            "{}",
+            report
+        );
+        assert_eq!(report.max_gate_degree, 3);
+        assert_eq!(report.lookups, 0);
+        assert_eq!(report.permutation_columns, 2);
+        assert_eq!(report.used_rows, 4);
+        assert!(report.used_rows + report.blinding_rows <= report.rows());
+        assert!(report.proof_size > 0);
+
+        // one more row than what fits in 2^k doubles the rows
+        let rows = report.rows() - report.blinding_rows;
+        let fits = cost_report(&MyCircuit { rows }, &[vec![Fp::zero()]]).unwrap();
+        assert_eq!(fits.k, report.k);
+        let too_large = cost_report(&MyCircuit { rows: rows + 1 }, &[vec![Fp::zero()]]).unwrap();
+        assert_eq!(too_large.k, report.k + 1);
+    }
+}
@@ -0,0 +1,647 @@
+// Goal: audit the gates of a circuit ("f(a, b, c) = if a == b {c} else {a - b}", "is_zero", "range check"...)
+// without reading the code that configures them. describe() walks the ConstraintSystem of a circuit and prints
+// every column, selector, gate polynomial and lookup argument in algebraic notation:
+//
+//   gate 'is_zero':
+//     [degree 4] s0 * (a - b) * (1 - (a - b) * value_inv)
+//
+// The columns have no names in the ConstraintSystem, so the circuit is synthesized without witnesses into a Recorder
+// and each column is named after the annotation most of its cells got (spaces become _). Columns that are never
+// assigned are named advice_0, fixed_1... and selectors are named s0, s1... after their index.
+// to_json() gives the same description in a stable JSON format for auditors and diff tools: the order of every list
+// is the order of the configure calls and the keys are always written in the same order.
+
+use std::fmt;
+
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    plonk::{Any, Circuit, ConstraintSystem, Error, Expression, Selector},
+};
+
+use super::diagnostics::format_value;
+use super::recorder::Recorder;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnDescription {
+    // advice, fixed or instance
+    pub column_type: &'static str,
+    pub index: usize,
+    pub name: String,
+    // the column takes part in copy constraints
+    pub equality: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelectorDescription {
+    pub name: String,
+    pub simple: bool,
+    // "gate 'name'" or "lookup i"
+    pub used_by: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConstraintDescription {
+    // empty when the constraint has no name
+    pub name: String,
+    pub polynomial: String,
+    pub degree: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GateDescription {
+    pub name: String,
+    pub constraints: Vec<ConstraintDescription>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LookupDescription {
+    pub index: usize,
+    // (inputs) must be a row of (table)
+    pub inputs: Vec<String>,
+    pub table: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CircuitDescription {
+    pub columns: Vec<ColumnDescription>,
+    pub selectors: Vec<SelectorDescription>,
+    pub gates: Vec<GateDescription>,
+    pub lookups: Vec<LookupDescription>,
+}
+
+// the name of every column, by type and index
+struct Names {
+    advice: Vec<String>,
+    fixed: Vec<String>,
+    instance: Vec<String>,
+}
+
+impl Names {
+    fn new<F: FieldExt>(cs: &ConstraintSystem<F>, recorder: &Recorder<F>) -> Self {
+        let sanitize = |name: &str| -> String {
+            name.chars()
+                .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+                .collect()
+        };
+
+        // the annotation most cells of the column got, the first one on a tie
+        let advice = (0..cs.num_advice_columns())
+            .map(|column| {
+                let mut counts: Vec<(&str, usize)> = vec![];
+                for advice in recorder
+                    .advice
+                    .iter()
+                    .filter(|((index, _), _)| *index == column)
+                    .map(|(_, advice)| advice)
+                {
+                    match counts.iter_mut().find(|(name, _)| *name == advice.annotation) {
+                        Some((_, count)) => *count += 1,
+                        None => counts.push((advice.annotation.as_str(), 1)),
+                    }
+                }
+                let mut best: Option<(&str, usize)> = None;
+                for (name, count) in counts {
+                    if best.map_or(true, |(_, best)| count > best) {
+                        best = Some((name, count));
+                    }
+                }
+                best.map_or(format!("advice_{}", column), |(name, _)| sanitize(name))
+            })
+            .collect();
+
+        // the constants are assigned with their value as annotation, so they get a name of their own
+        let fixed = (0..cs.num_fixed_columns())
+            .map(|column| {
+                if cs.constants().iter().any(|constant| constant.index() == column) {
+                    "constants".to_string()
+                } else {
+                    recorder
+                        .fixed_annotations
+                        .get(&column)
+                        .map_or(format!("fixed_{}", column), |name| sanitize(name))
+                }
+            })
+            .collect();
+
+        let instance = (0..cs.num_instance_columns())
+            .map(|column| format!("instance_{}", column))
+            .collect();
+
+        let mut names = Names { advice, fixed, instance };
+        names.disambiguate();
+        names
+    }
+
+    // two columns with the same name get their type and index appended, e.g. value_advice_0 and value_advice_3
+    fn disambiguate(&mut self) {
+        let all: Vec<String> = self
+            .advice
+            .iter()
+            .chain(self.fixed.iter())
+            .chain(self.instance.iter())
+            .cloned()
+            .collect();
+        let is_duplicate = |name: &String| all.iter().filter(|other| *other == name).count() > 1;
+        for (column_type, names) in [
+            ("advice", &mut self.advice),
+            ("fixed", &mut self.fixed),
+            ("instance", &mut self.instance),
+        ] {
+            for (index, name) in names.iter_mut().enumerate() {
+                if is_duplicate(name) {
+                    *name = format!("{}_{}_{}", name, column_type, index);
+                }
+            }
+        }
+    }
+
+    fn column(&self, column_type: Any, index: usize) -> &str {
+        match column_type {
+            Any::Advice => &self.advice[index],
+            Any::Fixed => &self.fixed[index],
+            Any::Instance => &self.instance[index],
+        }
+    }
+}
+
+// The Debug of a Selector is Selector(index, simple), the index is not public
+pub(super) fn selector_index(selector: &Selector) -> usize {
+    let debug = format!("{:?}", selector);
+    debug
+        .trim_start_matches("Selector(")
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect::<String>()
+        .parse()
+        .unwrap_or(0)
+}
+
+// small constants in decimal, negative ones as -n, the others like MockProver does
+fn format_constant<F: FieldExt>(value: F) -> String {
+    let small = |value: F| {
+        let lower = value.get_lower_128();
+        (lower < 1 << 64 && value == F::from_u128(lower)).then_some(lower)
+    };
+    if let Some(value) = small(value) {
+        value.to_string()
+    } else if let Some(value) = small(-value) {
+        format!("-{}", value)
+    } else {
+        format_value(value)
+    }
+}
+
+const SUM: u8 = 0;
+const PRODUCT: u8 = 1;
+const UNARY: u8 = 2;
+const ATOM: u8 = 3;
+
+// the expression and its precedence, so that parentheses are only added where they are needed
+fn print<F: FieldExt>(expr: &Expression<F>, names: &Names) -> (String, u8) {
+    let wrap = |expr: &Expression<F>, min: u8| {
+        let (text, precedence) = print(expr, names);
+        if precedence < min {
+            format!("({})", text)
+        } else {
+            text
+        }
+    };
+    let query = |column_type: Any, column: usize, rotation: i32| {
+        let name = names.column(column_type, column);
+        if rotation == 0 {
+            name.to_string()
+        } else {
+            format!("{}[{}]", name, rotation)
+        }
+    };
+
+    match expr {
+        Expression::Constant(value) => {
+            let text = format_constant(*value);
+            let precedence = if text.starts_with('-') { UNARY } else { ATOM };
+            (text, precedence)
+        }
+        Expression::Selector(selector) => (format!("s{}", selector_index(selector)), ATOM),
+        Expression::Fixed { column_index, rotation, .. } => (query(Any::Fixed, *column_index, rotation.0), ATOM),
+        Expression::Advice { column_index, rotation, .. } => (query(Any::Advice, *column_index, rotation.0), ATOM),
+        Expression::Instance { column_index, rotation, .. } => (query(Any::Instance, *column_index, rotation.0), ATOM),
+        Expression::Negated(a) => (format!("-{}", wrap(a, UNARY)), UNARY),
+        // a + (-b) is printed a - b
+        Expression::Sum(a, b) => match &**b {
+            Expression::Negated(b) => (format!("{} - {}", wrap(a, SUM), wrap(b, PRODUCT)), SUM),
+            _ => (format!("{} + {}", wrap(a, SUM), wrap(b, PRODUCT)), SUM),
+        },
+        Expression::Product(a, b) => (format!("{} * {}", wrap(a, PRODUCT), wrap(b, UNARY)), PRODUCT),
+        Expression::Scaled(a, scalar) => {
+            let scalar = format_constant(*scalar);
+            let scalar = if scalar.starts_with('-') { format!("({})", scalar) } else { scalar };
+            (format!("{} * {}", wrap(a, PRODUCT), scalar), PRODUCT)
+        }
+    }
+}
+
+pub(super) fn selectors_of<F: FieldExt>(expr: &Expression<F>) -> Vec<Selector> {
+    expr.evaluate(
+        &|_| vec![],
+        &|selector| vec![selector],
+        &|_, _, _| vec![],
+        &|_, _, _| vec![],
+        &|_, _, _| vec![],
+        &|a| a,
+        &|mut a, b| {
+            a.extend(b);
+            a
+        },
+        &|mut a, b| {
+            a.extend(b);
+            a
+        },
+        &|a, _| a,
+    )
+}
+
+// Describe the ConstraintSystem of `circuit`, k only has to be large enough to synthesize it
+pub fn describe<F: FieldExt, C: Circuit<F>>(k: u32, circuit: &C) -> Result<CircuitDescription, Error> {
+    let (recorder, cs) = Recorder::synthesize(k, &circuit.without_witnesses(), vec![])?;
+    let names = Names::new(&cs, &recorder);
+
+    let equality = cs.permutation().get_columns();
+    let mut columns = vec![];
+    for (column_type, label, count) in [
+        (Any::Advice, "advice", cs.num_advice_columns()),
+        (Any::Fixed, "fixed", cs.num_fixed_columns()),
+        (Any::Instance, "instance", cs.num_instance_columns()),
+    ] {
+        for index in 0..count {
+            columns.push(ColumnDescription {
+                column_type: label,
+                index,
+                name: names.column(column_type, index).to_string(),
+                equality: equality
+                    .iter()
+                    .any(|column| *column.column_type() == column_type && column.index() == index),
+            });
+        }
+    }
+
+    let gates: Vec<GateDescription> = cs
+        .gates()
+        .iter()
+        .map(|gate| GateDescription {
+            name: gate.name().to_string(),
+            constraints: gate
+                .polynomials()
+                .iter()
+                .enumerate()
+                .map(|(index, polynomial)| ConstraintDescription {
+                    name: gate.constraint_name(index).to_string(),
+                    polynomial: print(polynomial, &names).0,
+                    degree: polynomial.degree(),
+                })
+                .collect(),
+        })
+        .collect();
+
+    let lookups: Vec<LookupDescription> = cs
+        .lookups()
+        .iter()
+        .enumerate()
+        .map(|(index, lookup)| LookupDescription {
+            index,
+            inputs: lookup
+                .input_expressions()
+                .iter()
+                .map(|expr| print(expr, &names).0)
+                .collect(),
+            table: lookup
+                .table_expressions()
+                .iter()
+                .map(|expr| print(expr, &names).0)
+                .collect(),
+        })
+        .collect();
+
+    // who queries each selector, in the order of the gates and the lookups
+    let mut selectors: Vec<(usize, SelectorDescription)> = vec![];
+    let mut use_selector = |selector: Selector, user: String| {
+        let index = selector_index(&selector);
+        let position = match selectors.iter().position(|(other, _)| *other == index) {
+            Some(position) => position,
+            None => {
+                selectors.push((
+                    index,
+                    SelectorDescription {
+                        name: format!("s{}", index),
+                        simple: selector.is_simple(),
+                        used_by: vec![],
+                    },
+                ));
+                selectors.len() - 1
+            }
+        };
+        let used_by = &mut selectors[position].1.used_by;
+        if !used_by.contains(&user) {
+            used_by.push(user);
+        }
+    };
+    for gate in cs.gates() {
+        for selector in gate.polynomials().iter().flat_map(selectors_of) {
+            use_selector(selector, format!("gate '{}'", gate.name()));
+        }
+    }
+    for (index, lookup) in cs.lookups().iter().enumerate() {
+        for selector in lookup.input_expressions().iter().flat_map(selectors_of) {
+            use_selector(selector, format!("lookup {}", index));
+        }
+    }
+    selectors.sort_by_key(|(index, _)| *index);
+
+    Ok(CircuitDescription {
+        columns,
+        selectors: selectors.into_iter().map(|(_, selector)| selector).collect(),
+        gates,
+        lookups,
+    })
+}
+
+impl fmt::Display for CircuitDescription {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "columns:")?;
+        for column in &self.columns {
+            writeln!(
+                f,
+                "  {} {} {}{}",
+                column.column_type,
+                column.index,
+                column.name,
+                if column.equality { " (equality)" } else { "" }
+            )?;
+        }
+        writeln!(f, "selectors:")?;
+        for selector in &self.selectors {
+            writeln!(
+                f,
+                "  {}{} used by {}",
+                selector.name,
+                if selector.simple { "" } else { " (complex)" },
+                selector.used_by.join(", ")
+            )?;
+        }
+        for gate in &self.gates {
+            writeln!(f, "gate '{}':", gate.name)?;
+            for constraint in &gate.constraints {
+                if constraint.name.is_empty() {
+                    writeln!(f, "  [degree {}] {}", constraint.degree, constraint.polynomial)?;
+                } else {
+                    writeln!(
+                        f,
+                        "  '{}' [degree {}] {}",
+                        constraint.name, constraint.degree, constraint.polynomial
+                    )?;
+                }
+            }
+        }
+        for lookup in &self.lookups {
+            writeln!(f, "lookup {}:", lookup.index)?;
+            writeln!(f, "  ({}) in ({})", lookup.inputs.join(", "), lookup.table.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+fn json_string(text: &str) -> String {
+    let mut json = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+fn json_strings(texts: &[String]) -> String {
+    let texts: Vec<String> = texts.iter().map(|text| json_string(text)).collect();
+    format!("[{}]", texts.join(", "))
+}
+
+// one item per line, so that diffs show the gates or the columns that changed
+fn json_list(items: Vec<String>) -> String {
+    if items.is_empty() {
+        "[]".to_string()
+    } else {
+        format!("[\n    {}\n  ]", items.join(",\n    "))
+    }
+}
+
+impl CircuitDescription {
+    pub fn to_json(&self) -> String {
+        let columns = self
+            .columns
+            .iter()
+            .map(|column| {
+                format!(
+                    "{{\"type\": {}, \"index\": {}, \"name\": {}, \"equality\": {}}}",
+                    json_string(column.column_type),
+                    column.index,
+                    json_string(&column.name),
+                    column.equality
+                )
+            })
+            .collect();
+        let selectors = self
+            .selectors
+            .iter()
+            .map(|selector| {
+                format!(
+                    "{{\"name\": {}, \"simple\": {}, \"used_by\": {}}}",
+                    json_string(&selector.name),
+                    selector.simple,
+                    json_strings(&selector.used_by)
+                )
+            })
+            .collect();
+        let gates = self
+            .gates
+            .iter()
+            .map(|gate| {
+                let constraints: Vec<String> = gate
+                    .constraints
+                    .iter()
+                    .map(|constraint| {
+                        format!(
+                            "{{\"name\": {}, \"polynomial\": {}, \"degree\": {}}}",
+                            json_string(&constraint.name),
+                            json_string(&constraint.polynomial),
+                            constraint.degree
+                        )
+                    })
+                    .collect();
+                format!(
+                    "{{\"name\": {}, \"constraints\": [{}]}}",
+                    json_string(&gate.name),
+                    constraints.join(", ")
+                )
+            })
+            .collect();
+        let lookups = self
+            .lookups
+            .iter()
+            .map(|lookup| {
+                format!(
+                    "{{\"index\": {}, \"inputs\": {}, \"table\": {}}}",
+                    lookup.index,
+                    json_strings(&lookup.inputs),
+                    json_strings(&lookup.table)
+                )
+            })
+            .collect();
+
+        format!(
+            "{{\n  \"columns\": {},\n  \"selectors\": {},\n  \"gates\": {},\n  \"lookups\": {}\n}}\n",
+            json_list(columns),
+            json_list(selectors),
+            json_list(gates),
+            json_list(lookups)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        pasta::Fp,
+        plonk::{Advice, Column, Constraints, Instance, TableColumn},
+        poly::Rotation,
+    };
+
+    #[derive(Debug, Clone)]
+    struct MyConfig {
+        a: Column<Advice>,
+        b: Column<Advice>,
+        instance: Column<Instance>,
+        q: Selector,
+        q_lookup: Selector,
+        table: TableColumn,
+    }
+
+    // b = 2 * a' - a where a' is a on the next row, and a is a 2-bit value
+    #[derive(Default)]
+    struct MyCircuit;
+
+    impl Circuit<Fp> for MyCircuit {
+        type Config = MyConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            let a = meta.advice_column();
+            let b = meta.advice_column();
+            let instance = meta.instance_column();
+            let q = meta.selector();
+            let q_lookup = meta.complex_selector();
+            let table = meta.lookup_table_column();
+            meta.enable_equality(b);
+            meta.enable_equality(instance);
+
+            meta.create_gate("double", |meta| {
+                let q = meta.query_selector(q);
+                let a = meta.query_advice(a, Rotation::cur());
+                let a_next = meta.query_advice(a, Rotation::next());
+                let b = meta.query_advice(b, Rotation::cur());
+                Constraints::with_selector(q, [("b", b - (a_next * Fp::from(2) - a))])
+            });
+            meta.lookup(|meta| {
+                let q_lookup = meta.query_selector(q_lookup);
+                let a = meta.query_advice(a, Rotation::cur());
+                vec![(q_lookup * a, table)]
+            });
+
+            MyConfig {
+                a,
+                b,
+                instance,
+                q,
+                q_lookup,
+                table,
+            }
+        }
+
+        fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<Fp>) -> Result<(), Error> {
+            layouter.assign_table(
+                || "2-bit table",
+                |mut table| {
+                    for value in 0..4 {
+                        table.assign_cell(|| "2-bit value", config.table, value, || Value::known(Fp::from(value as u64)))?;
+                    }
+                    Ok(())
+                },
+            )?;
+            let b = layouter.assign_region(
+                || "double",
+                |mut region| {
+                    config.q.enable(&mut region, 0)?;
+                    config.q_lookup.enable(&mut region, 0)?;
+                    region.assign_advice(|| "a", config.a, 0, || Value::known(Fp::one()))?;
+                    region.assign_advice(|| "a", config.a, 1, || Value::known(Fp::from(2)))?;
+                    region.assign_advice(|| "b", config.b, 0, || Value::known(Fp::from(3)))
+                },
+            )?;
+            layouter.constrain_instance(b.cell(), config.instance, 0)
+        }
+    }
+
+    #[test]
+    fn test_describe() {
+        let description = describe(4, &MyCircuit).unwrap();
+
+        assert_eq!(
+            description.to_string(),
+            "columns:
+  advice 0 a
+  advice 1 b (equality)
+  fixed 0 2_bit_value
+  instance 0 instance_0 (equality)
+selectors:
+  s0 used by gate 'double'
+  s1 (complex) used by lookup 0
+gate 'double':
+  'b' [degree 2] s0 * (b - (a[1] * 2 - a))
+lookup 0:
+  (s1 * a) in (2_bit_value)
+"
+        );
+
+        assert_eq!(
+            description.to_json(),
+            r#"{
+  "columns": [
+    {"type": "advice", "index": 0, "name": "a", "equality": false},
+    {"type": "advice", "index": 1, "name": "b", "equality": true},
+    {"type": "fixed", "index": 0, "name": "2_bit_value", "equality": false},
+    {"type": "instance", "index": 0, "name": "instance_0", "equality": true}
+  ],
+  "selectors": [
+    {"name": "s0", "simple": true, "used_by": ["gate 'double'"]},
+    {"name": "s1", "simple": false, "used_by": ["lookup 0"]}
+  ],
+  "gates": [
+    {"name": "double", "constraints": [{"name": "b", "polynomial": "s0 * (b - (a[1] * 2 - a))", "degree": 2}]}
+  ],
+  "lookups": [
+    {"index": 0, "inputs": ["s1 * a"], "table": ["2_bit_value"]}
+  ]
+}
+"#
+        );
+    }
+}
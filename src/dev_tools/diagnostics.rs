@@ -0,0 +1,602 @@
+// Goal: turn the VerifyFailures of MockProver into messages that talk about the chip instead of column indices:
+//   ConstraintNotSatisfied { constraint: Constraint 0 ('range check') in gate 0 ('range check'), cell_values: [(Column('Advice', 0)@0, "0x8")] .. }
+// becomes
+//   value 0x8 in region 'Assign value' is outside range [0, 8)
+// with the likely causes of the failure (a table that was never loaded, a gate without a selector...).
+//
+// The circuit is synthesized a second time into a Recorder, so that every failure can be mapped back to its row,
+// the annotations of the cells it reads and their values. That's a Failure, the chips turn it into a message with
+// an explain function (e.g. the one of the RangeCheckChip tests), the failures no chip knows about get a generic message.
+//
+//   diagnose(k, &circuit, instances, explain)
+
+use std::fmt;
+
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    dev::{FailureLocation, MockProver, VerifyFailure},
+    plonk::{Any, Circuit, ConstraintSystem, Expression},
+    poly::Rotation,
+};
+
+use super::recorder::Recorder;
+
+// What went wrong, the names come from the ConstraintSystem and the table names from assign_table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FailureKind {
+    // a constraint of an active gate doesn't evaluate to 0
+    Gate { gate: String, constraint: String },
+    // a constraint is active on the unusable rows at the end of the circuit
+    Poisoned { gate: String, constraint: String },
+    // an active gate queries a cell that is not assigned
+    CellNotAssigned { gate: String, column: String, offset: isize },
+    // the lookup input is not a row of the table, `table` is None when the table was never assigned
+    Lookup { index: usize, table: Option<String> },
+    // a copy constraint links cells with different values
+    Permutation { column: String },
+}
+
+// A VerifyFailure mapped back to the circuit
+#[derive(Debug, Clone)]
+pub struct Failure<F: FieldExt> {
+    pub kind: FailureKind,
+    // None outside any region
+    pub region: Option<String>,
+    pub row: Option<usize>,
+    // the advice cells read by the failing constraint or lookup, or the copied cells, by annotation
+    pub cells: Vec<(String, F)>,
+    // the values looked up, one per input expression of the lookup
+    pub inputs: Vec<F>,
+}
+
+impl<F: FieldExt> Failure<F> {
+    pub fn cell(&self, annotation: &str) -> Option<F> {
+        self.cells
+            .iter()
+            .find(|(name, _)| name == annotation)
+            .map(|(_, value)| *value)
+    }
+
+    pub fn region_name(&self) -> &str {
+        self.region.as_deref().unwrap_or("<outside any region>")
+    }
+
+    fn cells_to_string(&self) -> String {
+        let cells: Vec<String> = self
+            .cells
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, format_value(*value)))
+            .collect();
+        cells.join(", ")
+    }
+}
+
+// The generic message, when no chip explains the failure
+impl<F: FieldExt> fmt::Display for Failure<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            FailureKind::Gate { gate, constraint } if constraint.is_empty() => write!(
+                f,
+                "gate '{}' is not satisfied in region '{}' ({})",
+                gate,
+                self.region_name(),
+                self.cells_to_string()
+            ),
+            FailureKind::Gate { gate, constraint } => write!(
+                f,
+                "constraint '{}' of gate '{}' is not satisfied in region '{}' ({})",
+                constraint,
+                gate,
+                self.region_name(),
+                self.cells_to_string()
+            ),
+            FailureKind::Poisoned { gate, .. } => write!(f, "gate '{}' is active on the unusable rows", gate),
+            FailureKind::CellNotAssigned { gate, column, offset } => write!(
+                f,
+                "gate '{}' queries {} at offset {} of region '{}', which is not assigned",
+                gate,
+                column,
+                offset,
+                self.region_name()
+            ),
+            FailureKind::Lookup { index, table } => {
+                let inputs: Vec<String> = self.inputs.iter().map(|input| format_value(*input)).collect();
+                write!(
+                    f,
+                    "lookup {} for ({}) in region '{}' not found in {}",
+                    index,
+                    inputs.join(", "),
+                    self.region_name(),
+                    table.as_ref().map_or("an empty table".to_string(), |table| format!("'{}'", table))
+                )
+            }
+            FailureKind::Permutation { column } => write!(
+                f,
+                "copy constraint on {} broken in region '{}' ({})",
+                column,
+                self.region_name(),
+                self.cells_to_string()
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub message: String,
+    pub causes: Vec<String>,
+    // the VerifyFailure as displayed by MockProver
+    pub failure: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.message)?;
+        for cause in &self.causes {
+            writeln!(f, "  likely cause: {}", cause)?;
+        }
+        write!(f, "  MockProver: {}", self.failure)
+    }
+}
+
+// Formats a value like MockProver does: 0, 1, -1 or a hex number without leading zeros
+pub fn format_value<F: FieldExt>(value: F) -> String {
+    if value == F::zero() {
+        "0".into()
+    } else if value == F::one() {
+        "1".into()
+    } else if value == -F::one() {
+        "-1".into()
+    } else {
+        let hex = format!("{:?}", value);
+        format!("0x{}", hex.trim_start_matches("0x").trim_start_matches('0'))
+    }
+}
+
+// The metadata of a VerifyFailure (region, gate, constraint, column) has private fields, we read them back
+// from their Display: "Region 0 ('name')", "Gate 1 ('name')", "Constraint 0 ('name') in gate 1 ('name')", "Column('Advice', 0)".
+// That's the format of halo2_proofs::dev::metadata at the pinned rev, nothing guarantees it across versions:
+// the parsers panic with PARSE_ERROR instead of guessing, and test_parse_metadata fails as soon as the format changes.
+const PARSE_ERROR: &str = "unexpected Display of a VerifyFailure, the parsers of diagnostics.rs must follow halo2";
+
+fn number_after(text: &str, prefix: &str) -> Option<usize> {
+    let start = text.find(prefix)? + prefix.len();
+    let digits: String = text[start..].chars().take_while(|c| c.is_ascii_digit()).collect();
+    digits.parse().ok()
+}
+
+// the text between "('" and "')" after `prefix`
+fn name_after<'t>(text: &'t str, prefix: &str) -> Option<&'t str> {
+    let start = text.find(prefix)? + prefix.len();
+    let start = start + text[start..].find("('")? + 2;
+    let end = start + text[start..].find("')")?;
+    Some(&text[start..end])
+}
+
+// the name of a constraint, "" for an unnamed one: "Constraint 0 in gate 1 ('name')"
+fn constraint_name(constraint: &str) -> Option<&str> {
+    let rest = constraint
+        .strip_prefix("Constraint ")?
+        .trim_start_matches(|c: char| c.is_ascii_digit());
+    match rest.strip_prefix(" ('") {
+        Some(rest) => rest.split("') in gate ").next(),
+        None => rest.starts_with(" in gate ").then(|| ""),
+    }
+}
+
+fn column_after(text: &str) -> Option<(Any, usize)> {
+    let column_type = if text.contains("'Advice'") {
+        Any::Advice
+    } else if text.contains("'Fixed'") {
+        Any::Fixed
+    } else if text.contains("'Instance'") {
+        Any::Instance
+    } else {
+        return None;
+    };
+    Some((column_type, number_after(text, "', ")?))
+}
+
+fn evaluate<F: FieldExt>(expr: &Expression<F>, row: usize, recorder: &Recorder<F>) -> F {
+    let n = recorder.n() as i32;
+    let at = |rotation: Rotation| (row as i32 + rotation.0).rem_euclid(n) as usize;
+    expr.evaluate(
+        &|constant| constant,
+        &|selector| {
+            if recorder.selectors.contains(&(selector, row)) {
+                F::one()
+            } else {
+                F::zero()
+            }
+        },
+        &|_, column, rotation| recorder.fixed_value(column, at(rotation)),
+        // unassigned cells are 0 for MockProver too
+        &|_, column, rotation| recorder.advice_value(column, at(rotation)).unwrap_or_else(F::zero),
+        &|_, column, rotation| recorder.instance_value(column, at(rotation)),
+        &|a| -a,
+        &|a, b| a + b,
+        &|a, b| a * b,
+        &|a, scalar| a * scalar,
+    )
+}
+
+// every (column type, column index, row) the expression queries at `row`
+fn queried_columns<F: FieldExt>(expr: &Expression<F>, row: usize, n: usize) -> Vec<(Any, usize, usize)> {
+    let at = |rotation: Rotation| (row as i32 + rotation.0).rem_euclid(n as i32) as usize;
+    expr.evaluate(
+        &|_| vec![],
+        &|_| vec![],
+        &|_, column, rotation| vec![(Any::Fixed, column, at(rotation))],
+        &|_, column, rotation| vec![(Any::Advice, column, at(rotation))],
+        &|_, column, rotation| vec![(Any::Instance, column, at(rotation))],
+        &|a| a,
+        &|mut a, b| {
+            a.extend(b);
+            a
+        },
+        &|mut a, b| {
+            a.extend(b);
+            a
+        },
+        &|a, _| a,
+    )
+}
+
+// the annotation and the value of a cell, fixed and instance cells are named after their column
+fn named_cell<F: FieldExt>(recorder: &Recorder<F>, column_type: Any, column: usize, row: usize) -> (String, F) {
+    match column_type {
+        Any::Advice => (
+            recorder
+                .advice
+                .get(&(column, row))
+                .map_or(format!("advice column {}", column), |advice| advice.annotation.clone()),
+            recorder.advice_value(column, row).unwrap_or_else(F::zero),
+        ),
+        Any::Fixed => (format!("fixed column {}", column), recorder.fixed_value(column, row)),
+        Any::Instance => (format!("instance column {}", column), recorder.instance_value(column, row)),
+    }
+}
+
+// the advice cells read by the expressions at `row`, in query order and without duplicates
+fn advice_cells<'e, F: FieldExt>(
+    exprs: impl Iterator<Item = &'e Expression<F>>,
+    row: usize,
+    recorder: &Recorder<F>,
+) -> Vec<(String, F)> {
+    let mut cells = vec![];
+    for expr in exprs {
+        for (column_type, column, row) in queried_columns(expr, row, recorder.n()) {
+            if column_type == Any::Advice && !cells.contains(&(column, row)) {
+                cells.push((column, row));
+            }
+        }
+    }
+    cells
+        .into_iter()
+        .map(|(column, row)| named_cell(recorder, Any::Advice, column, row))
+        .collect()
+}
+
+// region name and row of a failure
+fn locate<F: FieldExt>(location: &FailureLocation, recorder: &Recorder<F>) -> (Option<String>, Option<usize>) {
+    match location {
+        FailureLocation::InRegion { region, offset } => {
+            let index = number_after(&region.to_string(), "Region ").expect(PARSE_ERROR);
+            let region = recorder.regions.get(index);
+            (
+                region.map(|region| region.name.clone()),
+                region.and_then(|region| region.rows).map(|(start, _)| start + offset),
+            )
+        }
+        FailureLocation::OutsideRegion { row } => (None, Some(*row)),
+    }
+}
+
+fn gate_failure<F: FieldExt>(
+    constraint: &impl fmt::Display,
+    row: Option<usize>,
+    cs: &ConstraintSystem<F>,
+    recorder: &Recorder<F>,
+) -> (String, String, Vec<(String, F)>) {
+    let constraint = constraint.to_string();
+    let gate = name_after(&constraint, " in gate ").expect(PARSE_ERROR).to_string();
+    let name = constraint_name(&constraint).expect(PARSE_ERROR).to_string();
+    let gate_index = number_after(&constraint, " in gate ").expect(PARSE_ERROR);
+    let index = number_after(&constraint, "Constraint ").expect(PARSE_ERROR);
+    let polynomial = cs.gates().get(gate_index).and_then(|gate| gate.polynomials().get(index));
+    let cells = match (polynomial, row) {
+        (Some(polynomial), Some(row)) => advice_cells(std::iter::once(polynomial), row, recorder),
+        _ => vec![],
+    };
+    (gate, name, cells)
+}
+
+impl<F: FieldExt> Failure<F> {
+    fn new(failure: &VerifyFailure, cs: &ConstraintSystem<F>, recorder: &Recorder<F>) -> Self {
+        let no_location = (None, None);
+        let ((region, row), kind, cells, inputs) = match failure {
+            VerifyFailure::ConstraintNotSatisfied { constraint, location, .. } => {
+                let (region, row) = locate(location, recorder);
+                let (gate, constraint, cells) = gate_failure(constraint, row, cs, recorder);
+                ((region, row), FailureKind::Gate { gate, constraint }, cells, vec![])
+            }
+            VerifyFailure::ConstraintPoisoned { constraint } => {
+                let (gate, constraint, _) = gate_failure(constraint, None, cs, recorder);
+                (no_location, FailureKind::Poisoned { gate, constraint }, vec![], vec![])
+            }
+            VerifyFailure::CellNotAssigned {
+                gate,
+                region,
+                column,
+                offset,
+                ..
+            } => {
+                let gate = gate.to_string();
+                let region = number_after(&region.to_string(), "Region ").expect(PARSE_ERROR);
+                let region = recorder.regions.get(region).map(|region| region.name.clone());
+                let kind = FailureKind::CellNotAssigned {
+                    gate: name_after(&gate, "Gate ").expect(PARSE_ERROR).to_string(),
+                    column: format!("{:?} column {}", column.column_type(), column.index()),
+                    offset: *offset,
+                };
+                ((region, None), kind, vec![], vec![])
+            }
+            VerifyFailure::Lookup { lookup_index, location } => {
+                let (region, row) = locate(location, recorder);
+                let lookup = &cs.lookups()[*lookup_index];
+                // the table is the region in which its columns were assigned
+                let table = lookup
+                    .table_expressions()
+                    .iter()
+                    .flat_map(|expr| queried_columns(expr, 0, recorder.n()))
+                    .find_map(|(_, column, _)| recorder.fixed_regions.get(&column).copied().flatten())
+                    .map(|region| recorder.regions[region].name.clone());
+                let (cells, inputs) = match row {
+                    Some(row) => (
+                        advice_cells(lookup.input_expressions().iter(), row, recorder),
+                        lookup
+                            .input_expressions()
+                            .iter()
+                            .map(|expr| evaluate(expr, row, recorder))
+                            .collect(),
+                    ),
+                    None => (vec![], vec![]),
+                };
+                let kind = FailureKind::Lookup {
+                    index: *lookup_index,
+                    table,
+                };
+                ((region, row), kind, cells, inputs)
+            }
+            VerifyFailure::Permutation { column, location } => {
+                let (region, row) = locate(location, recorder);
+                let column = column.to_string();
+                let mut cells = vec![];
+                let (column_type, index) = column_after(&column).expect(PARSE_ERROR);
+                if let Some(row) = row {
+                    cells.push(named_cell(recorder, column_type, index, row));
+                    // the cells it is copied from or to
+                    for (left, right) in &recorder.copies {
+                        for (this, other) in [(left, right), (right, left)] {
+                            if *this.0.column_type() == column_type && this.0.index() == index && this.1 == row {
+                                cells.push(named_cell(recorder, *other.0.column_type(), other.0.index(), other.1));
+                            }
+                        }
+                    }
+                }
+                ((region, row), FailureKind::Permutation { column }, cells, vec![])
+            }
+        };
+
+        Failure {
+            kind,
+            region,
+            row,
+            cells,
+            inputs,
+        }
+    }
+
+    // What usually leads to this kind of failure
+    fn causes(&self) -> Vec<String> {
+        match &self.kind {
+            FailureKind::Gate { gate, .. } if self.region.is_none() => vec![format!(
+                "gate '{}' fails outside of any region: its constraints are probably missing a selector",
+                gate
+            )],
+            FailureKind::Gate { .. } => vec![
+                "the witness is wrong: a chip assigned a value its gate doesn't accept".to_string(),
+                "the selector is enabled on a row that the gate is not meant for".to_string(),
+            ],
+            FailureKind::Poisoned { gate, .. } => vec![format!(
+                "gate '{}' is active on every row, including the unusable ones: it is probably missing a selector",
+                gate
+            )],
+            FailureKind::CellNotAssigned { .. } => vec![
+                "the region forgot to assign a cell that the gate queries".to_string(),
+                "the gate queries the cell at the wrong rotation".to_string(),
+            ],
+            FailureKind::Lookup { table: None, .. } => vec![
+                "the lookup table was never loaded: call load_table (or load) of the chip in synthesize".to_string(),
+            ],
+            FailureKind::Lookup { .. } if self.region.is_none() => vec![
+                "the lookup is active outside any region: its inputs are not multiplied by a selector".to_string(),
+                "the value of the disabled rows (the inputs with the selector off) is not a row of the table".to_string(),
+            ],
+            FailureKind::Lookup { .. } => vec![
+                "the value is not in the table: it's out of range or the wrong table is used".to_string(),
+                "the lookup selector is enabled on a row that is not meant to be looked up".to_string(),
+            ],
+            FailureKind::Permutation { .. } => vec![
+                "a copied cell was assigned again with another value: use copy_advice instead of assign_advice".to_string(),
+                "the public input doesn't match the cell constrained to it with constrain_instance".to_string(),
+            ],
+        }
+    }
+}
+
+// Run MockProver and explain its failures. `explain` gives the chip-level message of a failure,
+// or None to fall back to the generic one
+pub fn diagnose<F: FieldExt, C: Circuit<F>>(
+    k: u32,
+    circuit: &C,
+    instances: Vec<Vec<F>>,
+    explain: impl Fn(&Failure<F>) -> Option<String>,
+) -> Result<(), Vec<Diagnostic>> {
+    let prover = MockProver::run(k, circuit, instances.clone()).unwrap();
+    let failures = match prover.verify() {
+        Ok(()) => return Ok(()),
+        Err(failures) => failures,
+    };
+
+    let (recorder, cs) = Recorder::synthesize(k, circuit, instances).unwrap();
+    Err(failures
+        .iter()
+        .map(|failure| {
+            let mapped = Failure::new(failure, &cs, &recorder);
+            Diagnostic {
+                message: explain(&mapped).unwrap_or_else(|| mapped.to_string()),
+                causes: mapped.causes(),
+                failure: failure.to_string(),
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        pasta::Fp,
+        plonk::{Advice, Column, ConstraintSystem, Error, Selector, TableColumn},
+    };
+
+    #[derive(Debug, Clone)]
+    struct MyConfig {
+        a: Column<Advice>,
+        b: Column<Advice>,
+        q: Selector,
+        table: TableColumn,
+    }
+
+    // a = b and b is looked up in a 2-bit table, which is loaded only if `load_table`
+    #[derive(Default)]
+    struct MyCircuit {
+        a: u64,
+        b: u64,
+        load_table: bool,
+    }
+
+    impl Circuit<Fp> for MyCircuit {
+        type Config = MyConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self {
+                load_table: self.load_table,
+                ..Self::default()
+            }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            let a = meta.advice_column();
+            let b = meta.advice_column();
+            let q = meta.complex_selector();
+            let table = meta.lookup_table_column();
+            meta.create_gate("a = b", |meta| {
+                let q = meta.query_selector(q);
+                let a = meta.query_advice(a, Rotation::cur());
+                let b = meta.query_advice(b, Rotation::cur());
+                vec![q * (a - b)]
+            });
+            meta.lookup(|meta| {
+                let q = meta.query_selector(q);
+                let b = meta.query_advice(b, Rotation::cur());
+                vec![(q * b, table)]
+            });
+            MyConfig { a, b, q, table }
+        }
+
+        fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<Fp>) -> Result<(), Error> {
+            layouter.assign_region(
+                || "a = b",
+                |mut region| {
+                    config.q.enable(&mut region, 0)?;
+                    region.assign_advice(|| "a", config.a, 0, || Value::known(Fp::from(self.a)))?;
+                    region.assign_advice(|| "b", config.b, 0, || Value::known(Fp::from(self.b)))?;
+                    Ok(())
+                },
+            )?;
+            if self.load_table {
+                layouter.assign_table(
+                    || "2-bit table",
+                    |mut table| {
+                        for value in 0..4 {
+                            table.assign_cell(|| "value", config.table, value, || Value::known(Fp::from(value as u64)))?;
+                        }
+                        Ok(())
+                    },
+                )?;
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_diagnose() {
+        assert_eq!(format_value(Fp::from(300)), "0x12c");
+        assert_eq!(format_value(-Fp::one()), "-1");
+
+        let circuit = |a, b, load_table| MyCircuit { a, b, load_table };
+        assert_eq!(diagnose(4, &circuit(2, 2, true), vec![], |_| None), Ok(()));
+
+        let diagnostics = diagnose(4, &circuit(2, 3, true), vec![], |_| None).unwrap_err();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].message, "gate 'a = b' is not satisfied in region 'a = b' (a=0x2, b=0x3)");
+
+        let diagnostics = diagnose(4, &circuit(5, 5, true), vec![], |_| None).unwrap_err();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].message, "lookup 0 for (0x5) in region 'a = b' not found in '2-bit table'");
+
+        // without the table, only 0 can be looked up
+        let diagnostics = diagnose(4, &circuit(2, 2, false), vec![], |_| None).unwrap_err();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].message, "lookup 0 for (0x2) in region 'a = b' not found in an empty table");
+        assert!(diagnostics[0].causes[0].contains("never loaded"));
+
+        // the chip-level message replaces the generic one
+        let diagnostics = diagnose(4, &circuit(5, 5, true), vec![], |failure| {
+            let b = failure.cell("b")?;
+            matches!(failure.kind, FailureKind::Lookup { .. }).then(|| format!("b = {} is not a 2-bit value", format_value(b)))
+        })
+        .unwrap_err();
+        assert_eq!(diagnostics[0].message, "b = 0x5 is not a 2-bit value");
+    }
+
+    #[test]
+    fn test_parse_metadata() {
+        use halo2_proofs::dev::metadata;
+
+        let region = metadata::Region::from((3, "a = b")).to_string();
+        assert_eq!(number_after(&region, "Region "), Some(3), "{}", region);
+
+        let gate = metadata::Gate::from((1, "a = b")).to_string();
+        assert_eq!(name_after(&gate, "Gate "), Some("a = b"), "{}", gate);
+
+        let named = metadata::Constraint::from(((1, "a = b").into(), 2, "a - b")).to_string();
+        assert_eq!(number_after(&named, "Constraint "), Some(2), "{}", named);
+        assert_eq!(number_after(&named, " in gate "), Some(1), "{}", named);
+        assert_eq!(name_after(&named, " in gate "), Some("a = b"), "{}", named);
+        assert_eq!(constraint_name(&named), Some("a - b"), "{}", named);
+
+        let unnamed = metadata::Constraint::from(((1, "a = b").into(), 0, "")).to_string();
+        assert_eq!(name_after(&unnamed, " in gate "), Some("a = b"), "{}", unnamed);
+        assert_eq!(constraint_name(&unnamed), Some(""), "{}", unnamed);
+
+        for column_type in [Any::Advice, Any::Fixed, Any::Instance] {
+            let column = metadata::Column::from((column_type, 4)).to_string();
+            assert_eq!(column_after(&column), Some((column_type, 4)), "{}", column);
+        }
+    }
+}
@@ -0,0 +1,259 @@
+// Goal: find soundness holes that nobody thought of. The tamper harness checks hand-picked attacks,
+// here we pick the attacks at random: after an honest run, every mutation forges one or more of the assigned advice cells
+// (small shifts of the honest value, 0 and 1, random field elements) and the circuit must reject the result.
+// The chips keep computing the other cells from the honest values: a forged cell that is copied somewhere else
+// differs from its copies, so the copy constraints are part of what must reject it. Mutations that still verify are reported with their region and cell names as candidate soundness holes:
+// some of them are just free inputs of the circuit (e.g. c in example3 when a != b), the others are missing constraints.
+//
+// There's no rand dependency, the xorshift generator of rng.rs seeded by the caller makes every run reproducible.
+
+use std::fmt;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use halo2_proofs::{arithmetic::FieldExt, dev::MockProver, plonk::Circuit};
+
+use super::rng::XorShift;
+use super::tamper::{AdviceSite, ForgedCell, Tampered};
+
+#[derive(Debug, Clone)]
+pub struct FuzzOptions {
+    pub seed: u64,
+    // number of mutations
+    pub iterations: usize,
+    // every mutation forges between 1 and max_cells cells
+    pub max_cells: usize,
+}
+
+impl Default for FuzzOptions {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            iterations: 64,
+            max_cells: 2,
+        }
+    }
+}
+
+// A forged advice cell
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mutation<F: FieldExt> {
+    pub region_index: usize,
+    pub region: String,
+    pub annotation: String,
+    pub column: usize,
+    pub offset: usize,
+    pub value: F,
+}
+
+impl<F: FieldExt> fmt::Display for Mutation<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "'{}' in region {} '{}' (advice column {}, offset {}) set to {:?}",
+            self.annotation, self.region_index, self.region, self.column, self.offset, self.value
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FuzzReport<F: FieldExt> {
+    pub rejected: usize,
+    // mutations that made the synthesis itself fail (an error or a panic of the witness generation)
+    pub skipped: usize,
+    // the mutations that verified, candidate soundness holes
+    pub survivors: Vec<Vec<Mutation<F>>>,
+}
+
+impl<F: FieldExt> fmt::Display for FuzzReport<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} rejected, {} skipped, {} survived",
+            self.rejected,
+            self.skipped,
+            self.survivors.len()
+        )?;
+        for mutations in &self.survivors {
+            let mutations: Vec<String> = mutations.iter().map(|mutation| mutation.to_string()).collect();
+            writeln!(f, "  survived: {}", mutations.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+// A value different from the honest one
+fn perturb<F: FieldExt>(rng: &mut XorShift, honest: F) -> F {
+    let delta = F::from(1 + rng.below(16) as u64);
+    let value = match rng.below(4) {
+        0 => honest + delta,
+        1 => honest - delta,
+        // forged booleans and inverses
+        2 => F::from(rng.below(2) as u64),
+        _ => rng.field(),
+    };
+    if value == honest {
+        value + F::one()
+    } else {
+        value
+    }
+}
+
+// Mutate the advice cells of `circuit` and report the mutations that MockProver accepts.
+// The honest witness must verify
+pub fn fuzz<F: FieldExt, C: Circuit<F>>(
+    k: u32,
+    circuit: C,
+    instances: Vec<Vec<F>>,
+    options: &FuzzOptions,
+) -> FuzzReport<F> {
+    let mut tampered = Tampered::new(circuit);
+    match tampered.verify(k, instances.clone()) {
+        Ok(Ok(())) => {}
+        Ok(Err(failures)) => panic!("the honest witness doesn't verify: {:#?}", failures),
+        Err(error) => panic!("the honest witness doesn't synthesize: {:?}", error),
+    }
+    let sites: Vec<AdviceSite<F>> = tampered.sites.take();
+
+    let mut rng = XorShift::new(options.seed);
+    let mut report = FuzzReport {
+        rejected: 0,
+        skipped: 0,
+        survivors: vec![],
+    };
+    if sites.is_empty() {
+        return report;
+    }
+
+    for _ in 0..options.iterations {
+        let num_cells = 1 + rng.below(options.max_cells.clamp(1, sites.len()));
+        let mut picked: Vec<usize> = vec![];
+        while picked.len() < num_cells {
+            let site = rng.below(sites.len());
+            if !picked.contains(&site) {
+                picked.push(site);
+            }
+        }
+
+        let mutations: Vec<Mutation<F>> = picked
+            .into_iter()
+            .map(|site| {
+                let site = &sites[site];
+                Mutation {
+                    region_index: site.region_index,
+                    region: site.region.clone(),
+                    annotation: site.annotation.clone(),
+                    column: site.column,
+                    offset: site.offset,
+                    value: perturb(&mut rng, site.value.unwrap_or_else(F::zero)),
+                }
+            })
+            .collect();
+
+        tampered.forged = mutations
+            .iter()
+            .map(|mutation| {
+                ForgedCell::new(
+                    &mutation.region,
+                    Some(mutation.region_index),
+                    mutation.column,
+                    mutation.offset,
+                    mutation.value,
+                )
+            })
+            .collect();
+        tampered.sites.borrow_mut().clear();
+
+        // the witness generation of the chips may panic on forged values (e.g. an overflow in native code)
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            MockProver::run(k, &tampered, instances.clone()).map(|prover| prover.verify())
+        }));
+        match result {
+            Ok(Ok(Ok(()))) => report.survivors.push(mutations),
+            Ok(Ok(Err(_))) => report.rejected += 1,
+            Ok(Err(_)) | Err(_) => report.skipped += 1,
+        }
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        pasta::Fp,
+        plonk::{Advice, Column, ConstraintSystem, Error, Selector},
+        poly::Rotation,
+    };
+
+    #[derive(Debug, Clone)]
+    struct MyConfig {
+        a: Column<Advice>,
+        b: Column<Advice>,
+        q: Selector,
+    }
+
+    // a = b on the first row, then a is assigned again on a row where the gate is disabled
+    #[derive(Default)]
+    struct MyCircuit;
+
+    impl Circuit<Fp> for MyCircuit {
+        type Config = MyConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            let a = meta.advice_column();
+            let b = meta.advice_column();
+            let q = meta.selector();
+            meta.create_gate("a = b", |meta| {
+                let q = meta.query_selector(q);
+                let a = meta.query_advice(a, Rotation::cur());
+                let b = meta.query_advice(b, Rotation::cur());
+                vec![q * (a - b)]
+            });
+            MyConfig { a, b, q }
+        }
+
+        fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<Fp>) -> Result<(), Error> {
+            layouter.assign_region(
+                || "a = b",
+                |mut region| {
+                    config.q.enable(&mut region, 0)?;
+                    region.assign_advice(|| "a", config.a, 0, || Value::known(Fp::one()))?;
+                    region.assign_advice(|| "b", config.b, 0, || Value::known(Fp::one()))?;
+                    region.assign_advice(|| "dangling a", config.a, 1, || Value::known(Fp::one()))?;
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    #[test]
+    fn test_fuzz() {
+        let options = FuzzOptions {
+            seed: 42,
+            iterations: 32,
+            max_cells: 1,
+        };
+        let report = fuzz(4, MyCircuit, vec![], &options);
+
+        // a and b can't change alone, the dangling cell can
+        assert_eq!(report.skipped, 0, "{}", report);
+        assert!(report.rejected > 0, "{}", report);
+        assert_eq!(report.rejected + report.survivors.len(), options.iterations);
+        assert!(!report.survivors.is_empty());
+        for mutations in &report.survivors {
+            assert_eq!(mutations.len(), 1);
+            assert_eq!(mutations[0].annotation, "dangling a");
+            assert_eq!((mutations[0].column, mutations[0].offset), (0, 1));
+        }
+
+        // the same seed gives the same report
+        assert_eq!(fuzz(4, MyCircuit, vec![], &options), report);
+    }
+}
@@ -0,0 +1,366 @@
+// Goal: see how the chips of a circuit are wired together (FunctionChip and the IsZeroChip it configures, the range
+// checks and their tables...) as a Graphviz graph. halo2 already draws the namespaces of the layouter
+// (namespace_graph below, halo2_proofs::dev::circuit_dot_graph), circuit_graph() adds what happens in them:
+//  - the namespaces are the chips, each one points to its nested namespaces and to the regions assigned in it
+//  - a region points to the gates whose selectors it enables, the edge is labelled with the selector
+//  - a copy constraint between two regions (or with a constant or a public input) is an edge "copy"
+//  - a lookup is a dashed edge from the regions that enable the selectors of its inputs to the table it reads
+//
+//   let dot = circuit_graph(&circuit)?.to_dot();
+//   std::fs::write("circuit.dot", dot)?;   // then: dot -Tsvg circuit.dot > circuit.svg
+//
+// The circuit is synthesized with its witnesses (and no public inputs) at the smallest k it fits in.
+
+use std::collections::{BTreeMap, HashMap};
+
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    plonk::{Any, Circuit, Column, Error, Expression},
+};
+use tabbycat::{
+    attributes::{label, shape, style, Shape, Style},
+    AttrList, Edge, GraphBuilder, GraphType, Identity, StmtList,
+};
+
+use super::cost::synthesize_min_k;
+use super::describe::{selector_index, selectors_of};
+use super::recorder::Recorder;
+
+// the chips and regions only, as halo2 draws them from the namespaces
+pub use halo2_proofs::dev::circuit_dot_graph as namespace_graph;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeKind {
+    // a namespace of the layouter
+    Chip,
+    Region,
+    // the region a lookup table was loaded in
+    Table,
+    Gate,
+    // the constants or an instance column
+    Column,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Node {
+    pub label: String,
+    pub kind: NodeKind,
+}
+
+#[derive(Debug, Default)]
+pub struct CircuitGraph {
+    nodes: Vec<Node>,
+    // each chip, region, gate or column gets a single node
+    keys: HashMap<String, usize>,
+    // (from, to, dashed) -> the labels of the edges between the two nodes, they are drawn as a single edge
+    edges: BTreeMap<(usize, usize, bool), Vec<String>>,
+}
+
+impl CircuitGraph {
+    fn node(&mut self, key: String, label: String, kind: NodeKind) -> usize {
+        if let Some(&id) = self.keys.get(&key) {
+            return id;
+        }
+        self.nodes.push(Node { label, kind });
+        self.keys.insert(key, self.nodes.len() - 1);
+        self.nodes.len() - 1
+    }
+
+    fn edge(&mut self, from: usize, to: usize, label: &str, dashed: bool) {
+        self.edges.entry((from, to, dashed)).or_default().push(label.to_string());
+    }
+
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    // true if there is an edge between the nodes labelled `from` and `to` with `label` among its labels
+    pub fn has_edge(&self, from: &str, to: &str, label: &str) -> bool {
+        self.edges.iter().any(|(&(head, tail, _), labels)| {
+            self.nodes[head].label == from && self.nodes[tail].label == to && labels.iter().any(|l| l == label)
+        })
+    }
+
+    // "copy (3), lookup 0": repeated labels are counted instead of being listed
+    fn edge_label(labels: &[String]) -> String {
+        let mut counts: Vec<(&str, usize)> = vec![];
+        for label in labels.iter().filter(|label| !label.is_empty()) {
+            match counts.iter_mut().find(|(other, _)| other == label) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((label.as_str(), 1)),
+            }
+        }
+        counts
+            .into_iter()
+            .map(|(label, count)| if count == 1 { label.to_string() } else { format!("{} ({})", label, count) })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    pub fn to_dot(&self) -> String {
+        // tabbycat works on string references, the labels have to outlive the statements
+        let edge_labels: Vec<String> = self.edges.values().map(|labels| Self::edge_label(labels)).collect();
+
+        let mut stmts = StmtList::new();
+        for (id, node) in self.nodes.iter().enumerate() {
+            let node_shape = match node.kind {
+                NodeKind::Chip => Shape::Box,
+                NodeKind::Region | NodeKind::Table => Shape::Ellipse,
+                NodeKind::Gate => Shape::Diamond,
+                NodeKind::Column => Shape::Plaintext,
+            };
+            let attributes = AttrList::new().add_pair(label(&node.label)).add_pair(shape(node_shape));
+            stmts = stmts.add_node(id.into(), None, Some(attributes));
+        }
+        for (&(from, to, dashed), text) in self.edges.keys().zip(&edge_labels) {
+            let mut edge = Edge::head_node(from.into(), None).arrow_to_node(to.into(), None);
+            if !text.is_empty() {
+                edge = edge.add_attrpair(label(text));
+            }
+            if dashed {
+                edge = edge.add_attrpair(style(Style::Dashed));
+            }
+            stmts = stmts.add_edge(edge);
+        }
+
+        GraphBuilder::default()
+            .graph_type(GraphType::DiGraph)
+            .strict(false)
+            .id(Identity::id("circuit").unwrap())
+            .stmts(stmts)
+            .build()
+            .unwrap()
+            .to_string()
+    }
+}
+
+fn fixed_columns_of<F: FieldExt>(expr: &Expression<F>) -> Vec<usize> {
+    expr.evaluate(
+        &|_| vec![],
+        &|_| vec![],
+        &|_, column, _| vec![column],
+        &|_, _, _| vec![],
+        &|_, _, _| vec![],
+        &|a| a,
+        &|mut a, b| {
+            a.extend(b);
+            a
+        },
+        &|mut a, b| {
+            a.extend(b);
+            a
+        },
+        &|a, _| a,
+    )
+}
+
+// the region a cell was assigned in, None for the constants and the public inputs
+fn cell_region<F: FieldExt>(recorder: &Recorder<F>, column: Column<Any>, row: usize) -> Option<usize> {
+    match column.column_type() {
+        Any::Advice => recorder.advice.get(&(column.index(), row)).and_then(|advice| advice.region),
+        Any::Fixed => recorder.fixed_regions.get(&column.index()).copied().flatten(),
+        Any::Instance => None,
+    }
+}
+
+// Build the graph of the chips, regions, gates, copies and lookups of `circuit`
+pub fn circuit_graph<F: FieldExt, C: Circuit<F>>(circuit: &C) -> Result<CircuitGraph, Error> {
+    let (recorder, cs) = synthesize_min_k(circuit, &[])?;
+    let mut graph = CircuitGraph::default();
+
+    // the table of a lookup is the region its fixed columns were assigned in
+    let tables: Vec<Option<usize>> = cs
+        .lookups()
+        .iter()
+        .map(|lookup| {
+            lookup
+                .table_expressions()
+                .iter()
+                .flat_map(fixed_columns_of)
+                .find_map(|column| recorder.fixed_regions.get(&column).copied().flatten())
+        })
+        .collect();
+
+    let mut regions = vec![];
+    for (index, region) in recorder.regions.iter().enumerate() {
+        let id = if tables.contains(&Some(index)) {
+            graph.node(format!("region {}", index), format!("table '{}'", region.name), NodeKind::Table)
+        } else {
+            graph.node(format!("region {}", index), format!("region '{}'", region.name), NodeKind::Region)
+        };
+        // each namespace points to the namespaces nested in it, the innermost one to the region
+        let mut parent = None;
+        for depth in 0..region.namespace.len() {
+            let path = region.namespace[..=depth].join("/");
+            let chip = graph.node(format!("namespace {}", path), region.namespace[depth].clone(), NodeKind::Chip);
+            if let Some(parent) = parent {
+                graph.edge(parent, chip, "", false);
+            }
+            parent = Some(chip);
+        }
+        if let Some(parent) = parent {
+            graph.edge(parent, id, "", false);
+        }
+        regions.push(id);
+    }
+
+    // sorted, so that the graph is the same from one run to the other
+    let mut selector_regions: Vec<_> = recorder.selector_regions.iter().copied().collect();
+    selector_regions.sort_by_key(|(selector, region)| (selector_index(selector), *region));
+
+    for (index, gate) in cs.gates().iter().enumerate() {
+        let id = graph.node(format!("gate {}", index), format!("gate '{}'", gate.name()), NodeKind::Gate);
+        let selectors: Vec<_> = gate.polynomials().iter().flat_map(selectors_of).collect();
+        for (selector, region) in &selector_regions {
+            if selectors.contains(selector) {
+                graph.edge(regions[*region], id, &format!("s{}", selector_index(selector)), false);
+            }
+        }
+    }
+
+    for ((left, left_row), (right, right_row)) in &recorder.copies {
+        let mut node = |column: &Column<Any>, row: usize| match cell_region(&recorder, *column, row) {
+            Some(region) => regions[region],
+            None if *column.column_type() == Any::Instance => graph.node(
+                format!("instance {}", column.index()),
+                format!("instance {}", column.index()),
+                NodeKind::Column,
+            ),
+            None => graph.node("constants".to_string(), "constants".to_string(), NodeKind::Column),
+        };
+        let (from, to) = (node(left, *left_row), node(right, *right_row));
+        // the copies inside a region would only clutter the graph
+        if from != to {
+            graph.edge(from, to, "copy", false);
+        }
+    }
+
+    for (index, lookup) in cs.lookups().iter().enumerate() {
+        let table = match tables[index] {
+            Some(region) => regions[region],
+            None => graph.node(
+                format!("lookup {}", index),
+                format!("table of lookup {} (never loaded)", index),
+                NodeKind::Table,
+            ),
+        };
+        let selectors: Vec<_> = lookup.input_expressions().iter().flat_map(selectors_of).collect();
+        for (selector, region) in &selector_regions {
+            if selectors.contains(selector) {
+                graph.edge(regions[*region], table, &format!("lookup {}", index), true);
+            }
+        }
+    }
+
+    Ok(graph)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        pasta::Fp,
+        plonk::{Advice, ConstraintSystem, Instance, Selector, TableColumn},
+        poly::Rotation,
+    };
+
+    #[derive(Debug, Clone)]
+    struct MyConfig {
+        a: Column<Advice>,
+        instance: Column<Instance>,
+        q_double: Selector,
+        q_lookup: Selector,
+        table: TableColumn,
+    }
+
+    // "double" checks a[1] = 2 * a[0] then copies a[1] into "small", which looks it up in a 2-bit table and
+    // exposes it. a[0] is the constant 1
+    struct MyCircuit;
+
+    impl Circuit<Fp> for MyCircuit {
+        type Config = MyConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            let a = meta.advice_column();
+            let instance = meta.instance_column();
+            let constant = meta.fixed_column();
+            let q_double = meta.selector();
+            let q_lookup = meta.complex_selector();
+            let table = meta.lookup_table_column();
+            meta.enable_equality(a);
+            meta.enable_equality(instance);
+            meta.enable_constant(constant);
+            meta.create_gate("double", |meta| {
+                let q = meta.query_selector(q_double);
+                let a_cur = meta.query_advice(a, Rotation::cur());
+                let a_next = meta.query_advice(a, Rotation::next());
+                vec![q * (a_next - a_cur * Fp::from(2))]
+            });
+            meta.lookup(|meta| {
+                let q = meta.query_selector(q_lookup);
+                let a = meta.query_advice(a, Rotation::cur());
+                vec![(q * a, table)]
+            });
+            MyConfig {
+                a,
+                instance,
+                q_double,
+                q_lookup,
+                table,
+            }
+        }
+
+        fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<Fp>) -> Result<(), Error> {
+            let doubled = layouter.namespace(|| "doubler").assign_region(
+                || "double",
+                |mut region| {
+                    config.q_double.enable(&mut region, 0)?;
+                    let one = region.assign_advice_from_constant(|| "one", config.a, 0, Fp::one())?;
+                    region.assign_advice(|| "two", config.a, 1, || one.value().map(|one| one + one))
+                },
+            )?;
+            let small = layouter.namespace(|| "range check").assign_region(
+                || "small",
+                |mut region| {
+                    config.q_lookup.enable(&mut region, 0)?;
+                    doubled.copy_advice(|| "small", &mut region, config.a, 0)
+                },
+            )?;
+            layouter.assign_table(
+                || "2-bit table",
+                |mut table| {
+                    for value in 0..4 {
+                        table.assign_cell(|| "value", config.table, value, || Value::known(Fp::from(value as u64)))?;
+                    }
+                    Ok(())
+                },
+            )?;
+            layouter.constrain_instance(small.cell(), config.instance, 0)
+        }
+    }
+
+    #[test]
+    fn test_circuit_graph() {
+        let graph = circuit_graph(&MyCircuit).unwrap();
+
+        assert!(graph.has_edge("doubler", "region 'double'", ""));
+        assert!(graph.has_edge("range check", "region 'small'", ""));
+        assert!(graph.has_edge("region 'double'", "gate 'double'", "s0"));
+        assert!(graph.has_edge("region 'double'", "region 'small'", "copy"));
+        // the constants are assigned after the regions, the copy goes from them to the region
+        assert!(graph.has_edge("constants", "region 'double'", "copy"));
+        assert!(graph.has_edge("region 'small'", "instance 0", "copy"));
+        assert!(graph.has_edge("region 'small'", "table '2-bit table'", "lookup 0"));
+        // the table region doesn't enable the gate
+        assert!(!graph.has_edge("table '2-bit table'", "gate 'double'", "s0"));
+
+        let dot = graph.to_dot();
+        assert!(dot.starts_with("digraph circuit"), "{}", dot);
+    }
+}
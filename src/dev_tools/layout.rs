@@ -0,0 +1,161 @@
+// Goal: render the layout of any circuit of the crate (FunctionCircuit, the range checks of example4 to example6...)
+// with a single call instead of copying the plotters boilerplate with a hand-picked size in each test.
+// render_layout() draws halo2's CircuitLayout into a PNG or an SVG file:
+//  - the size is derived from the number of columns and rows, unless LayoutOptions::size is set
+//  - the regions can be labelled, and the cells taking part in copy constraints marked
+//  - the rows reserved for the blinding factors can be hidden, they are never assigned by the circuit
+//
+//   render_layout(&circuit, 9, layout_path("range-check-2-layout", LayoutFormat::Svg), LayoutFormat::Svg)?;
+//
+// The tests write their layouts in target/layouts instead of the root of the repo.
+
+use std::{
+    error::Error,
+    path::{Path, PathBuf},
+};
+
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    dev::CircuitLayout,
+    plonk::{Circuit, ConstraintSystem},
+};
+use plotters::{
+    coord::Shift,
+    prelude::{BitMapBackend, DrawingArea, DrawingAreaErrorKind, DrawingBackend, IntoDrawingArea, SVGBackend, WHITE},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayoutFormat {
+    Png,
+    Svg,
+}
+
+impl LayoutFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            LayoutFormat::Png => "png",
+            LayoutFormat::Svg => "svg",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LayoutOptions {
+    // the name of the file when None
+    pub title: Option<String>,
+    // (width, height) in pixels, None to derive it from the number of columns and rows
+    pub size: Option<(u32, u32)>,
+    // write the name of each region in it
+    pub mark_regions: bool,
+    // mark the cells that take part in copy constraints, in red
+    pub mark_equality_cells: bool,
+    // only draw the usable rows
+    pub hide_blinding_rows: bool,
+}
+
+impl Default for LayoutOptions {
+    fn default() -> Self {
+        Self {
+            title: None,
+            size: None,
+            mark_regions: true,
+            mark_equality_cells: false,
+            hide_blinding_rows: false,
+        }
+    }
+}
+
+// Where the tests write their layouts: target/layouts/<name>.<extension>
+pub fn layout_path(name: &str, format: LayoutFormat) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("target")
+        .join("layouts")
+        .join(format!("{}.{}", name, format.extension()))
+}
+
+// 32 pixels per column and 8 per row (plus the title), within bounds that keep the file readable
+fn auto_size(columns: usize, rows: usize) -> (u32, u32) {
+    let width = (columns * 32).clamp(512, 4096);
+    let height = (rows * 8 + 100).clamp(512, 8192);
+    (width as u32, height as u32)
+}
+
+fn draw<F: FieldExt, C: Circuit<F>, DB: DrawingBackend>(
+    root: DrawingArea<DB, Shift>,
+    circuit: &C,
+    k: u32,
+    title: &str,
+    layout: CircuitLayout,
+) -> Result<(), DrawingAreaErrorKind<DB::ErrorType>> {
+    root.fill(&WHITE)?;
+    let area = root.titled(title, ("sans-serif", 40))?;
+    layout.render(k, circuit, &area)?;
+    root.present()
+}
+
+// Render the layout of `circuit` for 2^k rows into `path` with the default options
+pub fn render_layout<F: FieldExt, C: Circuit<F>>(
+    circuit: &C,
+    k: u32,
+    path: impl AsRef<Path>,
+    format: LayoutFormat,
+) -> Result<(), Box<dyn Error>> {
+    render_layout_with(circuit, k, path, format, &LayoutOptions::default())
+}
+
+pub fn render_layout_with<F: FieldExt, C: Circuit<F>>(
+    circuit: &C,
+    k: u32,
+    path: impl AsRef<Path>,
+    format: LayoutFormat,
+    options: &LayoutOptions,
+) -> Result<(), Box<dyn Error>> {
+    let path = path.as_ref();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let mut cs = ConstraintSystem::<F>::default();
+    C::configure(&mut cs);
+    let n = 1usize << k;
+    let rows = if options.hide_blinding_rows {
+        n.saturating_sub(cs.blinding_factors() + 1)
+    } else {
+        n
+    };
+    // the selectors are drawn as fixed columns
+    let columns = cs.num_instance_columns() + cs.num_advice_columns() + cs.num_fixed_columns() + cs.num_selectors();
+    let size = options.size.unwrap_or_else(|| auto_size(columns, rows));
+
+    let mut layout = CircuitLayout::default()
+        .show_labels(options.mark_regions)
+        .mark_equality_cells(options.mark_equality_cells);
+    if options.hide_blinding_rows {
+        layout = layout.view_height(0..rows);
+    }
+
+    let title = match &options.title {
+        Some(title) => title.clone(),
+        None => path.file_stem().map_or(String::new(), |stem| stem.to_string_lossy().into_owned()),
+    };
+
+    match format {
+        LayoutFormat::Png => draw(BitMapBackend::new(path, size).into_drawing_area(), circuit, k, &title, layout)?,
+        LayoutFormat::Svg => draw(SVGBackend::new(path, size).into_drawing_area(), circuit, k, &title, layout)?,
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_auto_size() {
+        // a few columns and rows still get a readable image
+        assert_eq!(auto_size(3, 16), (512, 512));
+        // the 256 rows of a table
+        assert_eq!(auto_size(6, 512), (512, 4196));
+        assert_eq!(auto_size(200, 1 << 20), (4096, 8192));
+    }
+}
@@ -0,0 +1,92 @@
+// Goal: check a chip against its native reference (IsZeroChip::native, FunctionChip::native, RangeCheckChip::native...)
+// on random inputs instead of a couple of hand-picked ones. The inputs come from a seeded XorShift so a failing case
+// can be replayed, and the failure message shows the case and its input.
+//  - check_output: the circuit constrains an output (e.g. exposed as a public input). It must verify with the reference
+//    output and fail with a wrong claimed output
+//  - check_acceptance: the circuit has no output but accepts or rejects its input (e.g. a range check),
+//    it must verify exactly when the reference accepts the input
+
+use std::fmt::Debug;
+
+use halo2_proofs::{arithmetic::FieldExt, dev::MockProver, plonk::Circuit};
+
+use super::rng::XorShift;
+
+// true if MockProver accepts the circuit. The circuit must synthesize: a synthesis error is a bug of the test
+// (a k that is too small, a missing witness), not a rejection by the constraints
+fn verifies<F: FieldExt, C: Circuit<F>>(k: u32, circuit: &C, instances: Vec<Vec<F>>, case: usize) -> bool {
+    match MockProver::run(k, circuit, instances) {
+        Ok(prover) => prover.verify().is_ok(),
+        Err(error) => panic!("case {}: the circuit doesn't synthesize: {:?}", case, error),
+    }
+}
+
+// `circuit` builds the circuit for an input and a claimed output, `wrong` picks a claimed output that is not the reference one
+pub fn check_output<F, I, O, C>(
+    k: u32,
+    seed: u64,
+    cases: usize,
+    mut input: impl FnMut(&mut XorShift) -> I,
+    reference: impl Fn(&I) -> O,
+    mut wrong: impl FnMut(&mut XorShift, &O) -> O,
+    circuit: impl Fn(&I, &O) -> (C, Vec<Vec<F>>),
+) where
+    F: FieldExt,
+    I: Debug,
+    O: Debug + PartialEq,
+    C: Circuit<F>,
+{
+    let mut rng = XorShift::new(seed);
+    for case in 0..cases {
+        let input = input(&mut rng);
+        let output = reference(&input);
+
+        let (honest, instances) = circuit(&input, &output);
+        let prover = MockProver::run(k, &honest, instances)
+            .unwrap_or_else(|error| panic!("case {}: the circuit doesn't synthesize: {:?}", case, error));
+        if let Err(failures) = prover.verify() {
+            panic!("case {}: {:?} -> {:?} doesn't verify: {:#?}", case, input, output, failures);
+        }
+
+        let claimed = wrong(&mut rng, &output);
+        assert_ne!(claimed, output, "case {}: the wrong output is the reference one", case);
+        let (dishonest, instances) = circuit(&input, &claimed);
+        assert!(
+            !verifies(k, &dishonest, instances, case),
+            "case {}: {:?} -> {:?} verifies, the reference output is {:?}",
+            case,
+            input,
+            claimed,
+            output
+        );
+    }
+}
+
+// `circuit` builds the circuit for an input, `reference` tells if the input must be accepted
+pub fn check_acceptance<F, I, C>(
+    k: u32,
+    seed: u64,
+    cases: usize,
+    mut input: impl FnMut(&mut XorShift) -> I,
+    reference: impl Fn(&I) -> bool,
+    circuit: impl Fn(&I) -> (C, Vec<Vec<F>>),
+) where
+    F: FieldExt,
+    I: Debug,
+    C: Circuit<F>,
+{
+    let mut rng = XorShift::new(seed);
+    for case in 0..cases {
+        let input = input(&mut rng);
+        let expected = reference(&input);
+        let (circuit, instances) = circuit(&input);
+        assert_eq!(
+            verifies(k, &circuit, instances, case),
+            expected,
+            "case {}: {:?} should be {}",
+            case,
+            input,
+            if expected { "accepted" } else { "rejected" }
+        );
+    }
+}
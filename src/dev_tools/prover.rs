@@ -0,0 +1,147 @@
+// Goal: stop guessing k. Every test picks k by hand (k = 9 for example5 was found by trial and error: 256 rows of table
+// + the rows reserved for the blinding factors don't fit in 2^8) and a k that is too small only shows up as
+// NotEnoughRowsAvailable. min_k() synthesizes the circuit with its floor planner, like cost_report() does, and returns
+// the smallest k its regions, tables, constants and public inputs fit in.
+// The entry points below use it instead of taking a k:
+//  - mock_prove: MockProver::run at the smallest k
+//  - prove_and_verify: keygen, proof and verification over the pasta curves at the smallest k, returns the proof
+//
+//   mock_prove(&circuit, vec![])?.assert_satisfied();
+//   let proof = prove_and_verify(&circuit, &[vec![Fp::from(6)]])?;
+
+use halo2_proofs::{arithmetic::FieldExt, dev::MockProver, plonk::Circuit, plonk::Error};
+use halo2_proofs::{
+    pasta::{EqAffine, Fp},
+    plonk::{create_proof, keygen_pk, keygen_vk, verify_proof, SingleVerifier},
+    poly::commitment::Params,
+    transcript::{Blake2bRead, Blake2bWrite, Challenge255},
+};
+use rand_core::OsRng;
+
+use super::cost::synthesize_min_k;
+
+// The smallest k `circuit` can be proven with, for these public inputs
+pub fn min_k<F: FieldExt, C: Circuit<F>>(circuit: &C, instances: &[Vec<F>]) -> Result<u32, Error> {
+    synthesize_min_k(circuit, instances).map(|(recorder, _)| recorder.k)
+}
+
+// MockProver::run with the smallest k
+pub fn mock_prove<F: FieldExt, C: Circuit<F>>(circuit: &C, instances: Vec<Vec<F>>) -> Result<MockProver<F>, Error> {
+    let k = min_k(circuit, &instances)?;
+    MockProver::run(k, circuit, instances)
+}
+
+// Generate the keys, prove `circuit` and verify the proof with the smallest k.
+// Unlike MockProver, a witness that doesn't satisfy the circuit only shows up as a verification error.
+pub fn prove_and_verify<C: Circuit<Fp>>(circuit: &C, instances: &[Vec<Fp>]) -> Result<Vec<u8>, Error> {
+    let k = min_k(circuit, instances)?;
+    let params: Params<EqAffine> = Params::new(k);
+    let vk = keygen_vk(&params, &circuit.without_witnesses())?;
+    let pk = keygen_pk(&params, vk, &circuit.without_witnesses())?;
+
+    let instances: Vec<&[Fp]> = instances.iter().map(Vec::as_slice).collect();
+    let mut transcript = Blake2bWrite::<_, EqAffine, Challenge255<_>>::init(vec![]);
+    create_proof(&params, &pk, &[circuit], &[&instances], OsRng, &mut transcript)?;
+    let proof = transcript.finalize();
+
+    let mut transcript = Blake2bRead::<_, EqAffine, Challenge255<_>>::init(&proof[..]);
+    verify_proof(&params, pk.get_vk(), SingleVerifier::new(&params), &[&instances], &mut transcript)?;
+    Ok(proof)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        plonk::{Advice, Column, ConstraintSystem, Instance, Selector},
+        poly::Rotation,
+    };
+
+    #[derive(Debug, Clone)]
+    struct MyConfig {
+        a: Column<Advice>,
+        b: Column<Advice>,
+        c: Column<Advice>,
+        instance: Column<Instance>,
+        q: Selector,
+    }
+
+    // c = a * b, c is public
+    #[derive(Default)]
+    struct MyCircuit {
+        a: Value<Fp>,
+        b: Value<Fp>,
+        c: Value<Fp>,
+    }
+
+    impl Circuit<Fp> for MyCircuit {
+        type Config = MyConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            let a = meta.advice_column();
+            let b = meta.advice_column();
+            let c = meta.advice_column();
+            let instance = meta.instance_column();
+            let q = meta.selector();
+            meta.enable_equality(c);
+            meta.enable_equality(instance);
+            meta.create_gate("c = a * b", |meta| {
+                let q = meta.query_selector(q);
+                let a = meta.query_advice(a, Rotation::cur());
+                let b = meta.query_advice(b, Rotation::cur());
+                let c = meta.query_advice(c, Rotation::cur());
+                vec![q * (c - a * b)]
+            });
+            MyConfig { a, b, c, instance, q }
+        }
+
+        fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<Fp>) -> Result<(), Error> {
+            let c = layouter.assign_region(
+                || "c = a * b",
+                |mut region| {
+                    config.q.enable(&mut region, 0)?;
+                    region.assign_advice(|| "a", config.a, 0, || self.a)?;
+                    region.assign_advice(|| "b", config.b, 0, || self.b)?;
+                    region.assign_advice(|| "c", config.c, 0, || self.c)
+                },
+            )?;
+            layouter.constrain_instance(c.cell(), config.instance, 0)
+        }
+    }
+
+    fn circuit(a: u64, b: u64, c: u64) -> MyCircuit {
+        MyCircuit {
+            a: Value::known(Fp::from(a)),
+            b: Value::known(Fp::from(b)),
+            c: Value::known(Fp::from(c)),
+        }
+    }
+
+    #[test]
+    fn test_min_k() {
+        // a single row, but the blinding factors still need 2^3 rows
+        let k = min_k(&circuit(2, 3, 6), &[vec![Fp::from(6)]]).unwrap();
+        assert_eq!(k, 3);
+        // the public inputs must fit in the usable rows too
+        let k = min_k(&circuit(2, 3, 6), &[vec![Fp::zero(); 10]]).unwrap();
+        assert_eq!(k, 4);
+
+        mock_prove(&circuit(2, 3, 6), vec![vec![Fp::from(6)]]).unwrap().assert_satisfied();
+        assert!(mock_prove(&circuit(2, 3, 7), vec![vec![Fp::from(7)]]).unwrap().verify().is_err());
+    }
+
+    #[test]
+    fn test_prove_and_verify() {
+        let proof = prove_and_verify(&circuit(2, 3, 6), &[vec![Fp::from(6)]]).unwrap();
+        assert!(!proof.is_empty());
+
+        // the prover doesn't check the witness, the verifier rejects the proof
+        assert!(prove_and_verify(&circuit(2, 3, 7), &[vec![Fp::from(7)]]).is_err());
+    }
+}
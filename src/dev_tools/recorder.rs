@@ -0,0 +1,233 @@
+// An implementation of Assignment that records what the floor planner assigns, the same way MockProver does,
+// but keeps the records accessible so that we can analyze them.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    circuit::Value,
+    plonk::{
+        Advice, Any, Assigned, Assignment, Circuit, Column, ConstraintSystem, Error, Fixed, FloorPlanner, Instance,
+        Selector,
+    },
+};
+
+#[derive(Debug, Clone)]
+pub(crate) struct RecordedRegion {
+    pub(crate) name: String,
+    // first and last row assigned in the region, if any
+    pub(crate) rows: Option<(usize, usize)>,
+    // the namespaces the region was assigned in, outermost first
+    pub(crate) namespace: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct RecordedAdvice<F: FieldExt> {
+    pub(crate) region: Option<usize>,
+    pub(crate) annotation: String,
+    pub(crate) value: Value<Assigned<F>>,
+}
+
+#[derive(Debug)]
+pub(crate) struct Recorder<F: FieldExt> {
+    pub(crate) k: u32,
+    // rows that can be assigned, the others are reserved for the blinding factors
+    pub(crate) usable_rows: usize,
+    // 1 + the last row assigned by a region, a table or the constants
+    pub(crate) used_rows: usize,
+    instances: Vec<Vec<F>>,
+    pub(crate) regions: Vec<RecordedRegion>,
+    current_region: Option<usize>,
+    namespace: Vec<String>,
+    // keyed by (column index, row)
+    pub(crate) advice: BTreeMap<(usize, usize), RecordedAdvice<F>>,
+    pub(crate) fixed: HashMap<(usize, usize), Assigned<F>>,
+    // region (or table) in which each fixed column was first assigned
+    pub(crate) fixed_regions: HashMap<usize, Option<usize>>,
+    // first annotation of each fixed column, e.g. the name of a table column
+    pub(crate) fixed_annotations: HashMap<usize, String>,
+    pub(crate) selectors: HashSet<(Selector, usize)>,
+    // (selector, region) for every region that enables the selector
+    pub(crate) selector_regions: HashSet<(Selector, usize)>,
+    pub(crate) copies: Vec<((Column<Any>, usize), (Column<Any>, usize))>,
+}
+
+impl<F: FieldExt> Recorder<F> {
+    // Configure and synthesize `circuit` for 2^k rows
+    pub(crate) fn synthesize<C: Circuit<F>>(
+        k: u32,
+        circuit: &C,
+        instances: Vec<Vec<F>>,
+    ) -> Result<(Self, ConstraintSystem<F>), Error> {
+        let mut cs = ConstraintSystem::default();
+        let config = C::configure(&mut cs);
+
+        let n = 1usize << k;
+        let reserved = cs.blinding_factors() + 1;
+        if n < cs.minimum_rows() {
+            return Err(Error::NotEnoughRowsAvailable { current_k: k });
+        }
+
+        let mut recorder = Recorder {
+            k,
+            usable_rows: n - reserved,
+            used_rows: 0,
+            instances,
+            regions: vec![],
+            current_region: None,
+            namespace: vec![],
+            advice: BTreeMap::new(),
+            fixed: HashMap::new(),
+            fixed_regions: HashMap::new(),
+            fixed_annotations: HashMap::new(),
+            selectors: HashSet::new(),
+            selector_regions: HashSet::new(),
+            copies: vec![],
+        };
+        C::FloorPlanner::synthesize(&mut recorder, circuit, config, cs.constants().clone())?;
+        Ok((recorder, cs))
+    }
+
+    pub(crate) fn n(&self) -> usize {
+        1 << self.k
+    }
+
+    // name of the region an advice cell was assigned in
+    pub(crate) fn region_name(&self, region: Option<usize>) -> &str {
+        region.map_or("<outside any region>", |region| self.regions[region].name.as_str())
+    }
+
+    pub(crate) fn fixed_value(&self, column: usize, row: usize) -> F {
+        self.fixed.get(&(column, row)).map_or(F::zero(), |value| value.evaluate())
+    }
+
+    pub(crate) fn advice_value(&self, column: usize, row: usize) -> Option<F> {
+        let mut value = None;
+        if let Some(advice) = self.advice.get(&(column, row)) {
+            advice.value.map(|v| value = Some(v.evaluate()));
+        }
+        value
+    }
+
+    pub(crate) fn instance_value(&self, column: usize, row: usize) -> F {
+        self.instances
+            .get(column)
+            .and_then(|column| column.get(row))
+            .copied()
+            .unwrap_or_else(F::zero)
+    }
+
+    fn check_row(&mut self, row: usize) -> Result<(), Error> {
+        if row >= self.usable_rows {
+            return Err(Error::NotEnoughRowsAvailable { current_k: self.k });
+        }
+        self.used_rows = self.used_rows.max(row + 1);
+        if let Some(region) = self.current_region {
+            let rows = &mut self.regions[region].rows;
+            *rows = Some(rows.map_or((row, row), |(start, end)| (start.min(row), end.max(row))));
+        }
+        Ok(())
+    }
+}
+
+impl<F: FieldExt> Assignment<F> for Recorder<F> {
+    fn enter_region<NR, N>(&mut self, name_fn: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        self.regions.push(RecordedRegion {
+            name: name_fn().into(),
+            rows: None,
+            namespace: self.namespace.clone(),
+        });
+        self.current_region = Some(self.regions.len() - 1);
+    }
+
+    fn exit_region(&mut self) {
+        self.current_region = None;
+    }
+
+    fn enable_selector<A, AR>(&mut self, _: A, selector: &Selector, row: usize) -> Result<(), Error>
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.check_row(row)?;
+        self.selectors.insert((*selector, row));
+        if let Some(region) = self.current_region {
+            self.selector_regions.insert((*selector, region));
+        }
+        Ok(())
+    }
+
+    fn query_instance(&self, column: Column<Instance>, row: usize) -> Result<Value<F>, Error> {
+        Ok(Value::known(self.instance_value(column.index(), row)))
+    }
+
+    fn assign_advice<V, VR, A, AR>(&mut self, annotation: A, column: Column<Advice>, row: usize, to: V) -> Result<(), Error>
+    where
+        V: FnOnce() -> Value<VR>,
+        VR: Into<Assigned<F>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.check_row(row)?;
+        self.advice.insert(
+            (column.index(), row),
+            RecordedAdvice {
+                region: self.current_region,
+                annotation: annotation().into(),
+                value: to().map(|value| value.into()),
+            },
+        );
+        Ok(())
+    }
+
+    fn assign_fixed<V, VR, A, AR>(&mut self, annotation: A, column: Column<Fixed>, row: usize, to: V) -> Result<(), Error>
+    where
+        V: FnOnce() -> Value<VR>,
+        VR: Into<Assigned<F>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.check_row(row)?;
+        self.fixed_regions.entry(column.index()).or_insert(self.current_region);
+        self.fixed_annotations
+            .entry(column.index())
+            .or_insert_with(|| annotation().into());
+        // fixed values are always known
+        to().map(|value| self.fixed.insert((column.index(), row), value.into()));
+        Ok(())
+    }
+
+    fn copy(
+        &mut self,
+        left_column: Column<Any>,
+        left_row: usize,
+        right_column: Column<Any>,
+        right_row: usize,
+    ) -> Result<(), Error> {
+        self.copies.push(((left_column, left_row), (right_column, right_row)));
+        Ok(())
+    }
+
+    fn fill_from_row(&mut self, column: Column<Fixed>, row: usize, to: Value<Assigned<F>>) -> Result<(), Error> {
+        for row in row..self.usable_rows {
+            to.map(|value| self.fixed.insert((column.index(), row), value));
+        }
+        Ok(())
+    }
+
+    fn push_namespace<NR, N>(&mut self, name_fn: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        self.namespace.push(name_fn().into());
+    }
+
+    fn pop_namespace(&mut self, _: Option<String>) {
+        self.namespace.pop();
+    }
+}
@@ -0,0 +1,36 @@
+// A tiny seeded random generator for the fuzzer and the property tests, so that they run offline and are reproducible
+// without a rand dependency. xorshift64* is good enough to pick cells and inputs, it's not meant for cryptography.
+
+use halo2_proofs::arithmetic::FieldExt;
+
+#[derive(Debug, Clone)]
+pub struct XorShift(u64);
+
+impl XorShift {
+    pub fn new(seed: u64) -> Self {
+        // the state must not be 0, xorshift would only ever output 0. The seed 0x9e37_79b9_7f4a_7c15 gets another state
+        let state = seed ^ 0x9e37_79b9_7f4a_7c15;
+        Self(if state == 0 { 1 } else { state })
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    pub fn next_u128(&mut self) -> u128 {
+        ((self.next_u64() as u128) << 64) | self.next_u64() as u128
+    }
+
+    // in 0..n
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    // a field element spread over the whole field
+    pub fn field<F: FieldExt>(&mut self) -> F {
+        F::from_u128(self.next_u128()) * F::from_u128(self.next_u128())
+    }
+}
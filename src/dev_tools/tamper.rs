@@ -0,0 +1,459 @@
+// Goal: play the malicious prover. The circuit is synthesized honestly, except that some advice cells chosen by the test
+// get an adversarial value instead of the one computed by the chip (a forged value_inv, an out-of-range value, a wrong output...).
+// MockProver must then reject the witness with the failure we expect, otherwise the chip is under-constrained.
+//
+// Tampered<F, C> wraps a circuit and its layouter: every region is handed a Region backed by TamperedRegion,
+// which forwards everything to the real region but swaps the value of the forged cells. The chip still gets the honest
+// value back in its AssignedCell, so the cells copied from a forged cell keep their honest value.
+// A cell is picked by (region name, advice column index, offset in the region) like in the VerifyFailure output.
+// The name must pick a single region: when several regions share it (e.g. InverseChip::assert_nonzero and invert
+// are both "invert") forge_nth picks the n-th of them in synthesis order. The fuzzer picks the region by its index.
+//
+//   let forged = Tampered::new(circuit).forge("f(a, b, c) = if a == b {c} else {a - b}", 4, 0, Fp::zero());
+//   forged.assert_rejected(k, vec![], Rejection::Gate("is_zero"));
+//
+// Most tests forge several witnesses of the same honest circuit, Forgeries keeps the circuit, the public inputs and
+// the smallest k so that every case only says what it forges and how it must be rejected:
+//
+//   let forgeries = Forgeries::new(|| circuit(5), vec![]);
+//   forgeries.forge_nth("invert", 0, 2, 0, Fp::zero()).assert_rejected(Rejection::Gate("inverse"));
+
+use std::{
+    cell::{Cell as Counter, RefCell},
+    collections::{BTreeSet, HashMap},
+};
+
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    circuit::{layouter::RegionLayouter, Cell, Layouter, Region, Table, Value},
+    dev::{MockProver, VerifyFailure},
+    plonk::{Advice, Assigned, Circuit, Column, ConstraintSystem, Error, Fixed, Instance, Selector},
+};
+
+use super::prover::min_k;
+
+#[derive(Debug)]
+pub(super) struct ForgedCell<F: FieldExt> {
+    region: String,
+    // the index of the region among all the regions, for the fuzzer
+    region_index: Option<usize>,
+    // the n-th region named `region`, for forge_nth
+    nth: Option<usize>,
+    column: usize,
+    offset: usize,
+    value: F,
+    // how many times the cell was assigned, a forged cell that is never assigned means a typo in the test
+    hits: Counter<usize>,
+    // the indexes of the regions that matched, a name alone must match a single one
+    regions: RefCell<BTreeSet<usize>>,
+}
+
+impl<F: FieldExt> ForgedCell<F> {
+    pub(super) fn new(region: &str, region_index: Option<usize>, column: usize, offset: usize, value: F) -> Self {
+        Self {
+            region: region.to_string(),
+            region_index,
+            nth: None,
+            column,
+            offset,
+            value,
+            hits: Counter::new(0),
+            regions: RefCell::new(BTreeSet::new()),
+        }
+    }
+
+    fn matches(&self, region_name: &str, region_index: usize, nth: usize) -> bool {
+        self.region == region_name
+            && self.region_index.map_or(true, |index| index == region_index)
+            && self.nth.map_or(true, |n| n == nth)
+    }
+}
+
+// An advice cell assigned with assign_advice (or copy_advice) during synthesis, and the value it got
+#[derive(Debug, Clone)]
+pub(super) struct AdviceSite<F: FieldExt> {
+    pub(super) region_index: usize,
+    pub(super) region: String,
+    pub(super) annotation: String,
+    pub(super) column: usize,
+    pub(super) offset: usize,
+    pub(super) value: Option<F>,
+}
+
+// What we expect MockProver to complain about
+#[derive(Debug, Clone, Copy)]
+pub enum Rejection<'a> {
+    // a constraint of the gate with this name
+    Gate(&'a str),
+    // the lookup with this index, in the order of the meta.lookup calls
+    Lookup(usize),
+    // a copy constraint
+    Permutation,
+}
+
+impl Rejection<'_> {
+    pub fn matches(&self, failure: &VerifyFailure) -> bool {
+        match (self, failure) {
+            (Rejection::Gate(name), VerifyFailure::ConstraintNotSatisfied { constraint, .. })
+            | (Rejection::Gate(name), VerifyFailure::ConstraintPoisoned { constraint }) => {
+                // the gate name is displayed as "in gate 0 ('name')"
+                constraint.to_string().contains(&format!("('{}')", name))
+            }
+            (Rejection::Lookup(index), VerifyFailure::Lookup { lookup_index, .. }) => index == lookup_index,
+            (Rejection::Permutation, VerifyFailure::Permutation { .. }) => true,
+            _ => false,
+        }
+    }
+}
+
+pub struct Tampered<F: FieldExt, C: Circuit<F>> {
+    circuit: C,
+    pub(super) forged: Vec<ForgedCell<F>>,
+    // every advice cell assigned by the last synthesis
+    pub(super) sites: RefCell<Vec<AdviceSite<F>>>,
+}
+
+impl<F: FieldExt, C: Circuit<F>> Tampered<F, C> {
+    pub fn new(circuit: C) -> Self {
+        Self {
+            circuit,
+            forged: vec![],
+            sites: RefCell::new(vec![]),
+        }
+    }
+
+    // Assign `value` to the advice cell at `offset` of `column` in the region named `region`, it must be the only one
+    pub fn forge(mut self, region: &str, column: usize, offset: usize, value: F) -> Self {
+        self.forged.push(ForgedCell::new(region, None, column, offset, value));
+        self
+    }
+
+    // Same as forge, in the `nth` region named `region` (from 0, in synthesis order)
+    pub fn forge_nth(mut self, region: &str, nth: usize, column: usize, offset: usize, value: F) -> Self {
+        let mut forged = ForgedCell::new(region, None, column, offset, value);
+        forged.nth = Some(nth);
+        self.forged.push(forged);
+        self
+    }
+
+    // Run MockProver on the tampered witness, the outer error is a failed synthesis
+    pub fn verify(&self, k: u32, instances: Vec<Vec<F>>) -> Result<Result<(), Vec<VerifyFailure>>, Error> {
+        let prover = MockProver::run(k, self, instances)?;
+        for forged in &self.forged {
+            assert!(
+                forged.hits.get() > 0,
+                "forged cell (column {}, offset {}) of region '{}' was never assigned",
+                forged.column,
+                forged.offset,
+                forged.region
+            );
+            let regions = forged.regions.borrow().len();
+            assert!(
+                forged.region_index.is_some() || forged.nth.is_some() || regions == 1,
+                "forged cell (column {}, offset {}) matches {} regions named '{}', pick one with forge_nth",
+                forged.column,
+                forged.offset,
+                regions,
+                forged.region
+            );
+        }
+        Ok(prover.verify())
+    }
+
+    // Panics unless MockProver rejects the tampered witness with the expected failure
+    pub fn assert_rejected(&self, k: u32, instances: Vec<Vec<F>>, expected: Rejection) {
+        match self.verify(k, instances) {
+            Err(error) => panic!("the tampered witness doesn't synthesize: {:?}, expected {:?}", error, expected),
+            Ok(Ok(())) => panic!("the tampered witness was accepted, expected {:?}", expected),
+            Ok(Err(failures)) => assert!(
+                failures.iter().any(|failure| expected.matches(failure)),
+                "expected {:?}, got {:#?}",
+                expected,
+                failures
+            ),
+        }
+    }
+}
+
+// An honest circuit, built by `circuit`, that every forgery starts from
+pub struct Forgeries<F: FieldExt, C: Circuit<F>, N: Fn() -> C> {
+    circuit: N,
+    instances: Vec<Vec<F>>,
+    k: u32,
+}
+
+impl<F: FieldExt, C: Circuit<F>, N: Fn() -> C> Forgeries<F, C, N> {
+    pub fn new(circuit: N, instances: Vec<Vec<F>>) -> Self {
+        let k = min_k(&circuit(), &instances).expect("the honest circuit doesn't synthesize");
+        Self { circuit, instances, k }
+    }
+
+    // A fresh honest circuit with a first forged cell, see Tampered::forge
+    pub fn forge(&self, region: &str, column: usize, offset: usize, value: F) -> Forgery<'_, F, C, N> {
+        Forgery {
+            forgeries: self,
+            tampered: Tampered::new((self.circuit)()).forge(region, column, offset, value),
+        }
+    }
+
+    // Same as forge, see Tampered::forge_nth
+    pub fn forge_nth(&self, region: &str, nth: usize, column: usize, offset: usize, value: F) -> Forgery<'_, F, C, N> {
+        Forgery {
+            forgeries: self,
+            tampered: Tampered::new((self.circuit)()).forge_nth(region, nth, column, offset, value),
+        }
+    }
+}
+
+pub struct Forgery<'a, F: FieldExt, C: Circuit<F>, N: Fn() -> C> {
+    forgeries: &'a Forgeries<F, C, N>,
+    tampered: Tampered<F, C>,
+}
+
+impl<F: FieldExt, C: Circuit<F>, N: Fn() -> C> Forgery<'_, F, C, N> {
+    pub fn forge(mut self, region: &str, column: usize, offset: usize, value: F) -> Self {
+        self.tampered = self.tampered.forge(region, column, offset, value);
+        self
+    }
+
+    pub fn forge_nth(mut self, region: &str, nth: usize, column: usize, offset: usize, value: F) -> Self {
+        self.tampered = self.tampered.forge_nth(region, nth, column, offset, value);
+        self
+    }
+
+    // Tampered::assert_rejected with the k and the public inputs of the honest circuit
+    pub fn assert_rejected(&self, expected: Rejection) {
+        let forgeries = self.forgeries;
+        self.tampered.assert_rejected(forgeries.k, forgeries.instances.clone(), expected);
+    }
+}
+
+impl<F: FieldExt, C: Circuit<F>> Circuit<F> for Tampered<F, C> {
+    type Config = C::Config;
+    type FloorPlanner = C::FloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::new(self.circuit.without_witnesses())
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        C::configure(meta)
+    }
+
+    fn synthesize(&self, config: Self::Config, layouter: impl Layouter<F>) -> Result<(), Error> {
+        self.circuit.synthesize(
+            config,
+            TamperedLayouter {
+                layouter,
+                forged: &self.forged,
+                sites: &self.sites,
+                next_region: 0,
+                occurrences: HashMap::new(),
+            },
+        )
+    }
+}
+
+struct TamperedLayouter<'a, F: FieldExt, L: Layouter<F>> {
+    layouter: L,
+    forged: &'a [ForgedCell<F>],
+    sites: &'a RefCell<Vec<AdviceSite<F>>>,
+    // regions and tables are counted in the order they are assigned, like MockProver does
+    next_region: usize,
+    // how many regions with each name were assigned so far
+    occurrences: HashMap<String, usize>,
+}
+
+impl<'a, F: FieldExt, L: Layouter<F>> Layouter<F> for TamperedLayouter<'a, F, L> {
+    type Root = Self;
+
+    fn assign_region<A, AR, N, NR>(&mut self, name: N, mut assignment: A) -> Result<AR, Error>
+    where
+        A: FnMut(Region<'_, F>) -> Result<AR, Error>,
+        N: Fn() -> NR,
+        NR: Into<String>,
+    {
+        let region_name: String = name().into();
+        let region_index = self.next_region;
+        self.next_region += 1;
+        let occurrence = self.occurrences.entry(region_name.clone()).or_insert(0);
+        let nth = *occurrence;
+        *occurrence += 1;
+        let forged: Vec<&ForgedCell<F>> = self
+            .forged
+            .iter()
+            .filter(|forged| forged.matches(&region_name, region_index, nth))
+            .collect();
+        for forged in &forged {
+            forged.regions.borrow_mut().insert(region_index);
+        }
+
+        let sites = self.sites;
+
+        self.layouter.assign_region(name, |region| {
+            let mut region = TamperedRegion {
+                region,
+                region_index,
+                region_name: &region_name,
+                forged: &forged,
+                sites,
+            };
+            assignment(Region::from(&mut region as &mut dyn RegionLayouter<F>))
+        })
+    }
+
+    fn assign_table<A, N, NR>(&mut self, name: N, assignment: A) -> Result<(), Error>
+    where
+        A: FnMut(Table<'_, F>) -> Result<(), Error>,
+        N: Fn() -> NR,
+        NR: Into<String>,
+    {
+        self.next_region += 1;
+        self.layouter.assign_table(name, assignment)
+    }
+
+    fn constrain_instance(&mut self, cell: Cell, column: Column<Instance>, row: usize) -> Result<(), Error> {
+        self.layouter.constrain_instance(cell, column, row)
+    }
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+
+    fn push_namespace<NR, N>(&mut self, name_fn: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        self.layouter.get_root().push_namespace(name_fn)
+    }
+
+    fn pop_namespace(&mut self, gadget_name: Option<String>) {
+        self.layouter.get_root().pop_namespace(gadget_name)
+    }
+}
+
+// Forwards to the real region, except for the advice cells that are forged
+#[derive(Debug)]
+struct TamperedRegion<'r, 'a, F: FieldExt> {
+    region: Region<'r, F>,
+    region_index: usize,
+    region_name: &'a str,
+    forged: &'a [&'a ForgedCell<F>],
+    sites: &'a RefCell<Vec<AdviceSite<F>>>,
+}
+
+impl<F: FieldExt> TamperedRegion<'_, '_, F> {
+    fn forged_value(&self, column: Column<Advice>, offset: usize) -> Option<F> {
+        self.forged
+            .iter()
+            .find(|forged| forged.column == column.index() && forged.offset == offset)
+            .map(|forged| {
+                forged.hits.set(forged.hits.get() + 1);
+                forged.value
+            })
+    }
+}
+
+impl<F: FieldExt> RegionLayouter<F> for TamperedRegion<'_, '_, F> {
+    fn enable_selector<'v>(
+        &'v mut self,
+        _annotation: &'v (dyn Fn() -> String + 'v),
+        selector: &Selector,
+        offset: usize,
+    ) -> Result<(), Error> {
+        selector.enable(&mut self.region, offset)
+    }
+
+    fn assign_advice<'v>(
+        &'v mut self,
+        annotation: &'v (dyn Fn() -> String + 'v),
+        column: Column<Advice>,
+        offset: usize,
+        to: &'v mut (dyn FnMut() -> Value<Assigned<F>> + 'v),
+    ) -> Result<Cell, Error> {
+        let forged = self.forged_value(column, offset);
+        let (region_index, region_name, sites) = (self.region_index, self.region_name, self.sites);
+        // the value is only computed when the cell is really assigned, not when the floor planner measures the region
+        self.region
+            .assign_advice(annotation, column, offset, || {
+                // `to` must run even for a forged cell: Region::assign_advice takes the value of the AssignedCell it
+                // returns to the chip from inside it, the chip would get an unknown value to copy around otherwise
+                let honest = to();
+                let value = match forged {
+                    Some(value) => Value::known(Assigned::from(value)),
+                    None => honest,
+                };
+                let mut site = AdviceSite {
+                    region_index,
+                    region: region_name.to_string(),
+                    annotation: annotation(),
+                    column: column.index(),
+                    offset,
+                    value: None,
+                };
+                value.as_ref().map(|value| site.value = Some(value.evaluate()));
+                sites.borrow_mut().push(site);
+                value
+            })
+            .map(|cell| cell.cell())
+    }
+
+    fn assign_advice_from_constant<'v>(
+        &'v mut self,
+        annotation: &'v (dyn Fn() -> String + 'v),
+        column: Column<Advice>,
+        offset: usize,
+        constant: Assigned<F>,
+    ) -> Result<Cell, Error> {
+        match self.forged_value(column, offset) {
+            // the same as assign_advice_from_constant, with the forged value in the advice cell
+            Some(value) => {
+                let cell = self
+                    .region
+                    .assign_advice(annotation, column, offset, || Value::known(value))?
+                    .cell();
+                self.region.constrain_constant(cell, constant)?;
+                Ok(cell)
+            }
+            None => self
+                .region
+                .assign_advice_from_constant(annotation, column, offset, constant)
+                .map(|cell| cell.cell()),
+        }
+    }
+
+    // cells copied from an instance column can't be forged, the copy to the instance isn't reachable from a region
+    fn assign_advice_from_instance<'v>(
+        &mut self,
+        annotation: &'v (dyn Fn() -> String + 'v),
+        instance: Column<Instance>,
+        row: usize,
+        advice: Column<Advice>,
+        offset: usize,
+    ) -> Result<(Cell, Value<F>), Error> {
+        self.region
+            .assign_advice_from_instance(annotation, instance, row, advice, offset)
+            .map(|cell| (cell.cell(), cell.value().copied()))
+    }
+
+    fn assign_fixed<'v>(
+        &'v mut self,
+        annotation: &'v (dyn Fn() -> String + 'v),
+        column: Column<Fixed>,
+        offset: usize,
+        to: &'v mut (dyn FnMut() -> Value<Assigned<F>> + 'v),
+    ) -> Result<Cell, Error> {
+        self.region
+            .assign_fixed(annotation, column, offset, to)
+            .map(|cell| cell.cell())
+    }
+
+    fn constrain_constant(&mut self, cell: Cell, constant: Assigned<F>) -> Result<(), Error> {
+        self.region.constrain_constant(cell, constant)
+    }
+
+    fn constrain_equal(&mut self, left: Cell, right: Cell) -> Result<(), Error> {
+        self.region.constrain_equal(left, right)
+    }
+}
@@ -1,4 +1,4 @@
-use crate::is_zero::{IsZeroChip, IsZeroConfig};
+use crate::is_zero::{IsZeroChip, IsZeroInstructions};
 use halo2_proofs::{
     arithmetic::FieldExt,
     circuit::{AssignedCell, Layouter, SimpleFloorPlanner, Value},
@@ -9,27 +9,37 @@ use halo2_proofs::{
 #[derive(Debug, Clone)]
 // We add the is_zero_config to the FunctionConfig as this is the gadget that we'll be using
 // The is_zero_config is the configuration for the IsZeroChip and is composed of an advice column and an expression
-struct FunctionConfig<F: FieldExt> {
+// The chip is generic over the IsZero gadget Z, any implementation of IsZeroInstructions will do
+struct FunctionConfig<F: FieldExt, Z: IsZeroInstructions<F>> {
     selector: Selector,
     a: Column<Advice>,
     b: Column<Advice>,
     c: Column<Advice>,
-    a_equals_b: IsZeroConfig<F>,
+    a_equals_b: Z::Config,
     output: Column<Advice>,
 }
 
 #[derive(Debug, Clone)]
-struct FunctionChip<F: FieldExt> {
-    config: FunctionConfig<F>,
+struct FunctionChip<F: FieldExt, Z: IsZeroInstructions<F>> {
+    config: FunctionConfig<F, Z>,
 }
 
-impl<F: FieldExt> FunctionChip<F> {
-    pub fn construct(config: FunctionConfig<F>) -> Self {
+impl<F: FieldExt, Z: IsZeroInstructions<F>> FunctionChip<F, Z> {
+    pub fn construct(config: FunctionConfig<F, Z>) -> Self {
         Self { config }
     }
 
+    // Native reference of the function, the output is constrained to be this
+    pub fn native(a: F, b: F, c: F) -> F {
+        if a == b {
+            c
+        } else {
+            a - b
+        }
+    }
+
     // Chip configuration. This is where we define the gates
-    pub fn configure(meta: &mut ConstraintSystem<F>) -> FunctionConfig<F> {
+    pub fn configure(meta: &mut ConstraintSystem<F>) -> FunctionConfig<F, Z> {
         let selector = meta.selector();
         let a = meta.advice_column();
         let b = meta.advice_column();
@@ -38,13 +48,15 @@ impl<F: FieldExt> FunctionChip<F> {
         let is_zero_advice_column = meta.advice_column();
 
         // We set the configuration for our gadget chip here!
-        let a_equals_b = IsZeroChip::configure(
+        let a_equals_b = Z::configure(
             meta,
             |meta| meta.query_selector(selector), // this is the q_enable
             |meta| meta.query_advice(a, Rotation::cur()) - meta.query_advice(b, Rotation::cur()), // this is the value
             is_zero_advice_column, // this is the advice column that stores value_inv
         );
 
+        let a_equals_b_expr = Z::expr(&a_equals_b);
+
         // We now need to set up our custom gate!
         meta.create_gate("f(a, b, c) = if a == b {c} else {a - b}", |meta| {
             let s = meta.query_selector(selector);
@@ -57,8 +69,8 @@ impl<F: FieldExt> FunctionChip<F> {
             // 10 | 10  | 15 | 1      | 1     |  15     | 1 * 1 * 0 (output == c)      | 1 * 0 * 15 = 0
             let output = meta.query_advice(output, Rotation::cur());
             vec![
-                s.clone() * (a_equals_b.expr() * (output.clone() - c)), // in this case output == c 
-                s * (Expression::Constant(F::one()) - a_equals_b.expr()) * (output - (a - b)), // in this case output == a - b
+                s.clone() * (a_equals_b_expr.clone() * (output.clone() - c)), // in this case output == c 
+                s * (Expression::Constant(F::one()) - a_equals_b_expr) * (output - (a - b)), // in this case output == a - b
             ]
         });
 
@@ -80,7 +92,7 @@ impl<F: FieldExt> FunctionChip<F> {
         b: F,
         c: F,
     ) -> Result<AssignedCell<F, F>, Error> {
-        let is_zero_chip = IsZeroChip::construct(self.config.a_equals_b.clone());
+        let is_zero_chip = Z::construct(self.config.a_equals_b.clone());
 
         layouter.assign_region(
             || "f(a, b, c) = if a == b {c} else {a - b}",
@@ -91,8 +103,7 @@ impl<F: FieldExt> FunctionChip<F> {
                 region.assign_advice(|| "c", self.config.c, 0, || Value::known(c))?;
                 // remember that the is_zero assign will assign the inverse of the value provided to the advice column
                 is_zero_chip.assign(&mut region, 0, Value::known(a - b))?;
-                let output = if a == b { c } else { a - b };
-                region.assign_advice(|| "output", self.config.output, 0, || Value::known(output))
+                region.assign_advice(|| "output", self.config.output, 0, || Value::known(Self::native(a, b, c)))
             },
         )
     }
@@ -106,7 +117,7 @@ struct FunctionCircuit<F> {
 }
 
 impl<F: FieldExt> Circuit<F> for FunctionCircuit<F> {
-    type Config = FunctionConfig<F>;
+    type Config = FunctionConfig<F, IsZeroChip<F>>;
     type FloorPlanner = SimpleFloorPlanner;
 
     fn without_witnesses(&self) -> Self {
@@ -127,7 +138,42 @@ impl<F: FieldExt> Circuit<F> for FunctionCircuit<F> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use halo2_proofs::{dev::MockProver, pasta::Fp};
+    use halo2_proofs::{
+        dev::MockProver,
+        pasta::Fp,
+        plonk::{Column, Instance},
+    };
+
+    // FunctionCircuit with the output exposed as a public input, so a wrong output can be claimed
+    #[derive(Default)]
+    struct PublicFunctionCircuit<F> {
+        a: F,
+        b: F,
+        c: F,
+    }
+
+    impl<F: FieldExt> Circuit<F> for PublicFunctionCircuit<F> {
+        type Config = (FunctionConfig<F, IsZeroChip<F>>, Column<Instance>);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            let config = FunctionChip::configure(meta);
+            let instance = meta.instance_column();
+            meta.enable_equality(config.output);
+            meta.enable_equality(instance);
+            (config, instance)
+        }
+
+        fn synthesize(&self, (config, instance): Self::Config, mut layouter: impl Layouter<F>) -> Result<(), Error> {
+            let chip = FunctionChip::construct(config);
+            let output = chip.assign(layouter.namespace(|| "f"), self.a, self.b, self.c)?;
+            layouter.constrain_instance(output.cell(), instance, 0)
+        }
+    }
 
     #[test]
     fn test_example3() {
@@ -139,5 +185,116 @@ mod tests {
 
         let prover = MockProver::run(4, &circuit, vec![]).unwrap();
         prover.assert_satisfied();
+        crate::dev_tools::assert_fully_constrained(4, &circuit, vec![]);
+    }
+
+    #[test]
+    fn test_example3_forged_witness() {
+        use crate::dev_tools::{Forgeries, Rejection};
+
+        let circuit = || FunctionCircuit {
+            a: Fp::from(10),
+            b: Fp::from(12),
+            c: Fp::from(15),
+        };
+        let forgeries = Forgeries::new(circuit, vec![]);
+        let region = "f(a, b, c) = if a == b {c} else {a - b}";
+
+        // value_inv = 0 claims that a - b is zero
+        forgeries
+            .forge(region, 4, 0, Fp::zero())
+            .assert_rejected(Rejection::Gate("is_zero"));
+
+        // a != b but the output is c
+        forgeries
+            .forge(region, 3, 0, Fp::from(15))
+            .assert_rejected(Rejection::Gate(region));
+    }
+
+    #[test]
+    fn test_example3_fuzz() {
+        use crate::dev_tools::{fuzz, FuzzOptions};
+
+        let circuit = FunctionCircuit {
+            a: Fp::from(10),
+            b: Fp::from(12),
+            c: Fp::from(15),
+        };
+        let report = fuzz(4, circuit, vec![], &FuzzOptions::default());
+        assert_eq!(report.skipped, 0, "{}", report);
+        assert!(report.rejected > 0, "{}", report);
+
+        // c is a free input when a != b, every other mutation must be rejected
+        for mutations in &report.survivors {
+            assert!(mutations.iter().all(|mutation| mutation.annotation == "c"), "{}", report);
+        }
+    }
+
+    #[test]
+    fn test_example3_reference() {
+        use crate::dev_tools::check_output;
+
+        check_output(
+            4,
+            0,
+            16,
+            // a == b half of the time, otherwise the output doesn't depend on c
+            |rng| {
+                let a: Fp = rng.field();
+                let b = if rng.below(2) == 0 { a } else { rng.field() };
+                (a, b, rng.field())
+            },
+            |&(a, b, c)| FunctionChip::<Fp, IsZeroChip<Fp>>::native(a, b, c),
+            |rng, output| *output + Fp::from(1 + rng.below(16) as u64),
+            |&(a, b, c), output| (PublicFunctionCircuit { a, b, c }, vec![vec![*output]]),
+        );
+    }
+
+    #[test]
+    fn test_example3_describe() {
+        let description = crate::dev_tools::describe(4, &FunctionCircuit::<Fp>::default()).unwrap();
+
+        let gate = |name: &str| description.gates.iter().find(|gate| gate.name == name).unwrap();
+        assert_eq!(
+            gate("is_zero").constraints[0].polynomial,
+            "s0 * (a - b) * (1 - (a - b) * value_inv)"
+        );
+        let function = gate("f(a, b, c) = if a == b {c} else {a - b}");
+        assert_eq!(
+            function.constraints[0].polynomial,
+            "s0 * ((1 - (a - b) * value_inv) * (output - c))"
+        );
+        assert_eq!(
+            function.constraints[1].polynomial,
+            "s0 * (1 - (1 - (a - b) * value_inv)) * (output - (a - b))"
+        );
+    }
+
+    #[cfg(feature = "dev-graph")]
+    #[test]
+    fn test_example3_graph() {
+        let circuit = PublicFunctionCircuit {
+            a: Fp::from(10),
+            b: Fp::from(12),
+            c: Fp::from(15),
+        };
+        let graph = crate::dev_tools::circuit_graph(&circuit).unwrap();
+
+        // the region of FunctionChip enables both its gate and the one of IsZeroChip
+        let region = "region 'f(a, b, c) = if a == b {c} else {a - b}'";
+        assert!(graph.has_edge("f", region, ""), "{}", graph.to_dot());
+        assert!(graph.has_edge(region, "gate 'is_zero'", "s0"));
+        assert!(graph.has_edge(region, "gate 'f(a, b, c) = if a == b {c} else {a - b}'", "s0"));
+        assert!(graph.has_edge(region, "instance 0", "copy"));
+    }
+
+    #[cfg(feature = "dev-graph")]
+    #[test]
+    fn print_example3() {
+        use crate::dev_tools::{layout_path, render_layout, LayoutFormat};
+
+        for format in [LayoutFormat::Png, LayoutFormat::Svg] {
+            render_layout(&FunctionCircuit::<Fp>::default(), 4, layout_path("example3-layout", format), format).unwrap();
+        }
     }
 }
@@ -0,0 +1,284 @@
+// Goal: prove y = f(x) for a non-polynomial native function f over a small domain, using a lookup.
+// It generalizes the RangeCheckTable of example5: instead of a range of values the table has all the (x, f(x)) pairs,
+// and the pair (x, y) of the advice columns must be one of its rows.
+// The function is a type implementing TableFunction, because the table is configured at keygen time (in `configure`)
+// where we don't have access to the circuit.
+//
+// The table is a Function table of a TableRegistry (see table_registry.rs): the pair is packed as x + DOMAIN * f(x)
+// under the tag of the function, and x is range checked against the range table of the registry.
+// Disabled rows hit the (0, 0) padding row of the registry, enabled rows can't claim it since their tag isn't 0.
+//
+//   x | y    | q_lookup | tag      | value
+//   3 | 9    | 1        | 0        | 0
+//   5 | 25   | 1        | range    | 0..DOMAIN
+//     |      | 0        | square   | x + DOMAIN * x^2
+
+use std::{fmt::Debug, marker::PhantomData};
+
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    circuit::{AssignedCell, Layouter},
+    plonk::{Advice, Column, ConstraintSystem, Error, Selector},
+    poly::Rotation,
+};
+
+use crate::table_registry::{LogicalTable, TableRegistry};
+
+// A native function over u64 that can be registered as a Function table
+pub trait TableFunction: Clone + Debug {
+    // used to name the table in the registry
+    const NAME: &'static str;
+
+    fn eval(x: u64) -> u64;
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Square;
+
+impl TableFunction for Square {
+    const NAME: &'static str = "square";
+
+    fn eval(x: u64) -> u64 {
+        x * x
+    }
+}
+
+// Number of bits needed to represent x, 0 for x = 0
+#[derive(Debug, Clone, Default)]
+pub struct BitLength;
+
+impl TableFunction for BitLength {
+    const NAME: &'static str = "bit length";
+
+    fn eval(x: u64) -> u64 {
+        (u64::BITS - x.leading_zeros()) as u64
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Popcount;
+
+impl TableFunction for Popcount {
+    const NAME: &'static str = "popcount";
+
+    fn eval(x: u64) -> u64 {
+        x.count_ones() as u64
+    }
+}
+
+// Fixed-point approximation of the sigmoid over 8 bits:
+// x in [0, 256) is read as t = (x - 128) / 16 in [-8, 8), and the output is round(255 * sigmoid(t)) in [0, 255]
+#[derive(Debug, Clone, Default)]
+pub struct Sigmoid;
+
+impl TableFunction for Sigmoid {
+    const NAME: &'static str = "sigmoid";
+
+    fn eval(x: u64) -> u64 {
+        let t = (x as f64 - 128.0) / 16.0;
+        (255.0 / (1.0 + (-t).exp())).round() as u64
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FunctionLookupConfig<F: FieldExt, Func: TableFunction, const DOMAIN: usize> {
+    x: Column<Advice>,
+    y: Column<Advice>,
+    q_lookup: Selector,
+    _marker: PhantomData<(F, Func)>,
+}
+
+pub struct FunctionLookupChip<F: FieldExt, Func: TableFunction, const DOMAIN: usize> {
+    config: FunctionLookupConfig<F, Func, DOMAIN>,
+}
+
+impl<F: FieldExt, Func: TableFunction, const DOMAIN: usize> FunctionLookupChip<F, Func, DOMAIN> {
+    pub fn construct(config: FunctionLookupConfig<F, Func, DOMAIN>) -> Self {
+        Self { config }
+    }
+
+    pub fn configure(
+        meta: &mut ConstraintSystem<F>,
+        x: Column<Advice>,
+        y: Column<Advice>,
+        registry: &mut TableRegistry<F>,
+    ) -> FunctionLookupConfig<F, Func, DOMAIN> {
+        // the registry tables are indexed by bits
+        assert!(DOMAIN.is_power_of_two(), "DOMAIN must be a power of two, got {}", DOMAIN);
+
+        meta.enable_equality(x);
+        meta.enable_equality(y);
+
+        // Simple selectors cannot appear in lookup arguments
+        let q_lookup = meta.complex_selector();
+        let tag = registry.register(LogicalTable::Function {
+            name: Func::NAME,
+            num_bits: DOMAIN.trailing_zeros() as usize,
+            f: Func::eval,
+        });
+
+        registry.lookup(
+            meta,
+            tag,
+            |meta| meta.query_selector(q_lookup),
+            |meta| vec![meta.query_advice(x, Rotation::cur()), meta.query_advice(y, Rotation::cur())],
+        );
+
+        FunctionLookupConfig {
+            x,
+            y,
+            q_lookup,
+            _marker: PhantomData,
+        }
+    }
+
+    // Returns f(x). The lookup fails if x is not in [0, DOMAIN)
+    // f is only evaluated on the domain: out of it f(x) could overflow the u64 (e.g. Square for x >= 2^32)
+    pub fn apply(&self, mut layouter: impl Layouter<F>, x: &AssignedCell<F, F>) -> Result<AssignedCell<F, F>, Error> {
+        layouter.assign_region(
+            || format!("apply {}", Func::NAME),
+            |mut region| {
+                self.config.q_lookup.enable(&mut region, 0)?;
+                x.copy_advice(|| "x", &mut region, self.config.x, 0)?;
+                // out of domain values still get a witness (any value would do), the lookup is what rejects them
+                let y = x.value().map(|x| {
+                    let x = x.get_lower_128();
+                    if x < DOMAIN as u128 {
+                        F::from(Func::eval(x as u64))
+                    } else {
+                        F::zero()
+                    }
+                });
+                region.assign_advice(|| "f(x)", self.config.y, 0, || y)
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use halo2_proofs::{
+        circuit::{SimpleFloorPlanner, Value},
+        dev::MockProver,
+        pasta::Fp,
+        plonk::{Circuit, Instance},
+    };
+
+    use crate::dev_tools::{min_k, mock_prove};
+
+    #[derive(Debug, Clone)]
+    struct MyConfig<F: FieldExt, Func: TableFunction, const DOMAIN: usize> {
+        input: Column<Advice>,
+        output: Column<Instance>,
+        function: FunctionLookupConfig<F, Func, DOMAIN>,
+        registry: TableRegistry<F>,
+    }
+
+    // exposes f(x) for every x
+    #[derive(Default)]
+    struct MyCircuit<F, Func, const DOMAIN: usize> {
+        xs: Vec<Value<F>>,
+        _marker: std::marker::PhantomData<Func>,
+    }
+
+    impl<F: FieldExt, Func: TableFunction + Default, const DOMAIN: usize> Circuit<F> for MyCircuit<F, Func, DOMAIN> {
+        type Config = MyConfig<F, Func, DOMAIN>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self {
+                xs: vec![Value::unknown(); self.xs.len()],
+                _marker: std::marker::PhantomData,
+            }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            let x = meta.advice_column();
+            let y = meta.advice_column();
+            let output = meta.instance_column();
+            meta.enable_equality(output);
+
+            let mut registry = TableRegistry::configure(meta);
+            let function = FunctionLookupChip::configure(meta, x, y, &mut registry);
+
+            MyConfig {
+                input: x,
+                output,
+                function,
+                registry,
+            }
+        }
+
+        fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<F>) -> Result<(), Error> {
+            let chip = FunctionLookupChip::construct(config.function);
+            config.registry.load(&mut layouter)?;
+
+            for (row, x) in self.xs.iter().enumerate() {
+                let x = layouter.assign_region(
+                    || "load x",
+                    |mut region| region.assign_advice(|| "x", config.input, 0, || *x),
+                )?;
+                let y = chip.apply(layouter.namespace(|| "f(x)"), &x)?;
+                layouter.constrain_instance(y.cell(), config.output, row)?;
+            }
+
+            Ok(())
+        }
+    }
+
+    fn circuit<Func: TableFunction + Default, const DOMAIN: usize>(xs: &[u64]) -> MyCircuit<Fp, Func, DOMAIN> {
+        MyCircuit {
+            xs: xs.iter().map(|x| Value::known(Fp::from(*x))).collect(),
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    fn run<Func: TableFunction + Default, const DOMAIN: usize>(xs: &[u64], ys: &[u64]) -> MockProver<Fp> {
+        let ys = ys.iter().map(|y| Fp::from(*y)).collect();
+        mock_prove(&circuit::<Func, DOMAIN>(xs), vec![ys]).unwrap()
+    }
+
+    #[test]
+    fn test_function_lookup() {
+        run::<Square, 16>(&[0, 3, 15], &[0, 9, 225]).assert_satisfied();
+        run::<BitLength, 256>(&[0, 1, 200], &[0, 1, 8]).assert_satisfied();
+        run::<Popcount, 256>(&[0, 7, 255], &[0, 3, 8]).assert_satisfied();
+        // sigmoid(0) = 0.5 and it saturates at both ends
+        run::<Sigmoid, 256>(&[0, 128, 255], &[0, 128, 255]).assert_satisfied();
+        let instances = vec![[0, 9, 225].map(Fp::from).to_vec()];
+        let k = min_k(&circuit::<Square, 16>(&[0, 3, 15]), &instances).unwrap();
+        crate::dev_tools::assert_fully_constrained(k, &circuit::<Square, 16>(&[0, 3, 15]), instances);
+
+        // wrong output
+        assert!(run::<Square, 16>(&[3], &[10]).verify().is_err());
+        // 16 is out of the domain
+        assert!(run::<Square, 16>(&[16], &[256]).verify().is_err());
+    }
+
+    #[test]
+    fn test_function_lookup_out_of_domain() {
+        use crate::dev_tools::Rejection;
+
+        // 2^32 squared overflows a u64, the witness must still be generated and rejected by the range check of x
+        let failures = run::<Square, 16>(&[1 << 32], &[0]).verify().unwrap_err();
+        assert!(
+            failures.iter().any(|failure| Rejection::Lookup(0).matches(failure)),
+            "expected a lookup failure, got {:#?}",
+            failures
+        );
+    }
+
+    #[test]
+    fn test_function_lookup_forged_witness() {
+        use crate::dev_tools::{Forgeries, Rejection};
+
+        let forgeries = Forgeries::new(|| circuit::<Square, 16>(&[3]), vec![vec![Fp::from(10)]]);
+
+        // claims 3^2 = 10 and exposes 10, only the lookup catches it. Column 1 is y,
+        // lookup 0 range checks x and lookup 1 is the square table
+        forgeries
+            .forge("apply square", 1, 0, Fp::from(10))
+            .assert_rejected(Rejection::Lookup(1));
+    }
+}
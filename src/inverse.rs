@@ -0,0 +1,274 @@
+// Goal: prove that a value is not zero by exhibiting its inverse, and give the inverse back as an assigned cell.
+// IsZeroChip already witnesses value_inv, but there a zero value gets 0 as its "inverse" and that's fine.
+// Here a zero value is an error: there's no inv such that value * inv = 1, so we fail in witness generation
+// instead of silently assigning 0 with `invert().unwrap_or(F::zero())` and getting a proof that doesn't verify.
+//
+//   a | value | inv       | out       | q_inverse | q_div
+//     | x     | 1 / x     |           | 1         | 0
+//   y | x     | 1 / x     | y / x     | 1         | 1
+
+use std::marker::PhantomData;
+
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    circuit::{AssignedCell, Layouter, Region},
+    plonk::{Advice, Column, ConstraintSystem, Constraints, Error, Expression, Selector},
+    poly::Rotation,
+};
+
+#[derive(Debug, Clone)]
+pub struct InverseConfig {
+    a: Column<Advice>,
+    value: Column<Advice>,
+    inv: Column<Advice>,
+    out: Column<Advice>,
+    q_inverse: Selector,
+    q_div: Selector,
+}
+
+pub struct InverseChip<F: FieldExt> {
+    config: InverseConfig,
+    _marker: PhantomData<F>,
+}
+
+impl<F: FieldExt> InverseChip<F> {
+    pub fn construct(config: InverseConfig) -> Self {
+        Self {
+            config,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn configure(meta: &mut ConstraintSystem<F>, advice: [Column<Advice>; 4]) -> InverseConfig {
+        let [a, value, inv, out] = advice;
+        for column in advice {
+            meta.enable_equality(column);
+        }
+
+        let q_inverse = meta.selector();
+        let q_div = meta.selector();
+
+        // there's no inv such that 0 * inv = 1, so this also proves that value != 0
+        meta.create_gate("inverse", |meta| {
+            let q_inverse = meta.query_selector(q_inverse);
+            let value = meta.query_advice(value, Rotation::cur());
+            let inv = meta.query_advice(inv, Rotation::cur());
+            Constraints::with_selector(
+                q_inverse,
+                [("value * inv = 1", value * inv - Expression::Constant(F::one()))],
+            )
+        });
+
+        meta.create_gate("div", |meta| {
+            let q_div = meta.query_selector(q_div);
+            let a = meta.query_advice(a, Rotation::cur());
+            let inv = meta.query_advice(inv, Rotation::cur());
+            let out = meta.query_advice(out, Rotation::cur());
+            Constraints::with_selector(q_div, [("out = a * inv", out - a * inv)])
+        });
+
+        InverseConfig {
+            a,
+            value,
+            inv,
+            out,
+            q_inverse,
+            q_div,
+        }
+    }
+
+    // Native reference: the inverse of value, None for 0 where the witness generation fails
+    pub fn native(value: F) -> Option<F> {
+        Option::from(value.invert())
+    }
+
+    // copy value into the region and assign its inverse, this fails if value is known to be zero
+    fn assign_inverse(
+        &self,
+        region: &mut Region<'_, F>,
+        value: &AssignedCell<F, F>,
+    ) -> Result<AssignedCell<F, F>, Error> {
+        value.value().error_if_known_and(|value| bool::from(value.is_zero()))?;
+
+        self.config.q_inverse.enable(region, 0)?;
+        value.copy_advice(|| "value", region, self.config.value, 0)?;
+        let inv = value.value().map(|value| value.invert().unwrap());
+        region.assign_advice(|| "inv", self.config.inv, 0, || inv)
+    }
+
+    // Returns the inverse of value
+    pub fn invert(&self, mut layouter: impl Layouter<F>, value: &AssignedCell<F, F>) -> Result<AssignedCell<F, F>, Error> {
+        layouter.assign_region(|| "invert", |mut region| self.assign_inverse(&mut region, value))
+    }
+
+    // Constrains value to be non zero
+    pub fn assert_nonzero(&self, layouter: impl Layouter<F>, value: &AssignedCell<F, F>) -> Result<(), Error> {
+        self.invert(layouter, value)?;
+        Ok(())
+    }
+
+    // Returns a / b, or an error if b is zero
+    pub fn safe_div(
+        &self,
+        mut layouter: impl Layouter<F>,
+        a: &AssignedCell<F, F>,
+        b: &AssignedCell<F, F>,
+    ) -> Result<AssignedCell<F, F>, Error> {
+        layouter.assign_region(
+            || "safe div",
+            |mut region| {
+                let inv = self.assign_inverse(&mut region, b)?;
+
+                self.config.q_div.enable(&mut region, 0)?;
+                a.copy_advice(|| "a", &mut region, self.config.a, 0)?;
+                let out = a.value().zip(inv.value()).map(|(a, inv)| *a * inv);
+                region.assign_advice(|| "out", self.config.out, 0, || out)
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use halo2_proofs::{
+        arithmetic::Field,
+        circuit::{SimpleFloorPlanner, Value},
+        dev::MockProver,
+        pasta::Fp,
+        plonk::{Circuit, Instance},
+    };
+
+    #[derive(Debug, Clone)]
+    struct MyConfig {
+        input: Column<Advice>,
+        output: Column<Instance>,
+        inverse: InverseConfig,
+    }
+
+    // exposes a / b and 1 / a
+    #[derive(Default)]
+    struct MyCircuit<F> {
+        a: Value<F>,
+        b: Value<F>,
+    }
+
+    impl<F: FieldExt> Circuit<F> for MyCircuit<F> {
+        type Config = MyConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            let advice = [
+                meta.advice_column(),
+                meta.advice_column(),
+                meta.advice_column(),
+                meta.advice_column(),
+            ];
+            let output = meta.instance_column();
+            meta.enable_equality(output);
+
+            MyConfig {
+                input: advice[0],
+                output,
+                inverse: InverseChip::configure(meta, advice),
+            }
+        }
+
+        fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<F>) -> Result<(), Error> {
+            let chip = InverseChip::construct(config.inverse);
+
+            let (a, b) = layouter.assign_region(
+                || "load inputs",
+                |mut region| {
+                    let a = region.assign_advice(|| "a", config.input, 0, || self.a)?;
+                    let b = region.assign_advice(|| "b", config.input, 1, || self.b)?;
+                    Ok((a, b))
+                },
+            )?;
+
+            chip.assert_nonzero(layouter.namespace(|| "a != 0"), &a)?;
+            let div = chip.safe_div(layouter.namespace(|| "a / b"), &a, &b)?;
+            let inv = chip.invert(layouter.namespace(|| "1 / a"), &a)?;
+
+            layouter.constrain_instance(div.cell(), config.output, 0)?;
+            layouter.constrain_instance(inv.cell(), config.output, 1)
+        }
+    }
+
+    #[test]
+    fn test_inverse() {
+        let k = 4;
+
+        let circuit = MyCircuit {
+            a: Value::known(Fp::from(6)),
+            b: Value::known(Fp::from(3)),
+        };
+        let inv = Fp::from(6).invert().unwrap();
+        let prover = MockProver::run(k, &circuit, vec![vec![Fp::from(2), inv]]).unwrap();
+        prover.assert_satisfied();
+        crate::dev_tools::assert_fully_constrained(k, &circuit, vec![vec![Fp::from(2), inv]]);
+
+        // division by zero is caught during witness generation
+        let circuit = MyCircuit {
+            a: Value::known(Fp::from(6)),
+            b: Value::known(Fp::zero()),
+        };
+        assert!(MockProver::run(k, &circuit, vec![vec![Fp::zero(), inv]]).is_err());
+    }
+
+    #[test]
+    fn test_inverse_reference() {
+        use crate::dev_tools::check_output;
+
+        check_output(
+            4,
+            0,
+            16,
+            |rng| (rng.field(), rng.field()),
+            |&(a, b): &(Fp, Fp)| {
+                let (a_inv, b_inv) = (InverseChip::native(a).unwrap(), InverseChip::native(b).unwrap());
+                (a * b_inv, a_inv)
+            },
+            // a wrong quotient, 1 / a is right
+            |rng, &(div, inv)| (div + Fp::from(1 + rng.below(16) as u64), inv),
+            |&(a, b), &(div, inv)| {
+                let circuit = MyCircuit {
+                    a: Value::known(a),
+                    b: Value::known(b),
+                };
+                (circuit, vec![vec![div, inv]])
+            },
+        );
+        assert_eq!(InverseChip::<Fp>::native(Fp::zero()), None);
+    }
+
+    #[test]
+    fn test_inverse_forged_witness() {
+        use crate::dev_tools::{Forgeries, Rejection};
+
+        let circuit = || MyCircuit {
+            a: Value::known(Fp::from(6)),
+            b: Value::known(Fp::from(3)),
+        };
+        let forgeries = Forgeries::new(circuit, vec![vec![Fp::from(2), Fp::from(6).invert().unwrap()]]);
+
+        // inv = 0 would let a zero value through, column 2 is inv. The first invert region is assert_nonzero
+        forgeries
+            .forge_nth("invert", 0, 2, 0, Fp::zero())
+            .assert_rejected(Rejection::Gate("inverse"));
+
+        // a wrong quotient, column 3 is out
+        forgeries
+            .forge("safe div", 3, 0, Fp::from(3))
+            .assert_rejected(Rejection::Gate("div"));
+
+        // a divisor that isn't the copied one, column 1 is value
+        forgeries
+            .forge("safe div", 1, 0, Fp::from(2))
+            .assert_rejected(Rejection::Permutation);
+    }
+}
@@ -1,5 +1,9 @@
 use halo2_proofs::{arithmetic::FieldExt, circuit::*, plonk::*, poly::Rotation};
 
+// a variant of the IsZero gadget that works across rows
+mod rotated;
+pub use rotated::RotatedIsZeroChip;
+
 #[derive(Clone, Debug)]
 
 pub struct IsZeroConfig<F> {
@@ -14,18 +18,58 @@ impl<F: FieldExt> IsZeroConfig<F> {
     }
 }
 
+// The instructions of an IsZero gadget, so that callers can swap implementations
+// (e.g. IsZeroChip or a variant that checks a value across rows)
+pub trait IsZeroInstructions<F: FieldExt>: Sized {
+    type Config: Clone + std::fmt::Debug;
+
+    fn construct(config: Self::Config) -> Self;
+
+    // q_enable is a selector to enable the gate. q_enable is a closure
+    // value is the value to be checked. Value is a closure
+    fn configure(
+        meta: &mut ConstraintSystem<F>,
+        q_enable: impl FnOnce(&mut VirtualCells<'_, F>) -> Expression<F>,
+        value: impl FnOnce(&mut VirtualCells<'_, F>) -> Expression<F>,
+        value_inv: Column<Advice>,
+    ) -> Self::Config;
+
+    // if value = 0, then the expression is 1, else it is 0
+    fn expr(config: &Self::Config) -> Expression<F>;
+
+    // The assignment function takes the actual value, generate the inverse of that and assign it to the advice column
+    fn assign(
+        &self,
+        region: &mut Region<'_, F>,
+        offset: usize,
+        value: Value<F>,
+    ) -> Result<(), Error>;
+}
+
+#[derive(Debug, Clone)]
 pub struct IsZeroChip<F: FieldExt> {
     config: IsZeroConfig<F>,
 }
 
 impl<F: FieldExt> IsZeroChip<F> {
-    pub fn construct(config: IsZeroConfig<F>) -> Self {
+    // Native reference of is_zero_expr: 1 if value is 0, 0 otherwise
+    pub fn native(value: F) -> F {
+        if bool::from(value.is_zero()) {
+            F::one()
+        } else {
+            F::zero()
+        }
+    }
+}
+
+impl<F: FieldExt> IsZeroInstructions<F> for IsZeroChip<F> {
+    type Config = IsZeroConfig<F>;
+
+    fn construct(config: IsZeroConfig<F>) -> Self {
         IsZeroChip { config }
     }
 
-    // q_enable is a selector to enable the gate. q_enable is a closure
-    // value is the value to be checked. Value is a closure
-    pub fn configure(
+    fn configure(
         meta: &mut ConstraintSystem<F>,
         q_enable: impl FnOnce(&mut VirtualCells<'_, F>) -> Expression<F>,
         value: impl FnOnce(&mut VirtualCells<'_, F>) -> Expression<F>,
@@ -63,8 +107,11 @@ impl<F: FieldExt> IsZeroChip<F> {
         }
     }
 
-    // The assignment function takes the actual value, generate the inverse of that and assign it to the advice column
-    pub fn assign(
+    fn expr(config: &IsZeroConfig<F>) -> Expression<F> {
+        config.expr()
+    }
+
+    fn assign(
         &self,
         region: &mut Region<'_, F>,
         offset: usize,
@@ -75,3 +122,113 @@ impl<F: FieldExt> IsZeroChip<F> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use halo2_proofs::pasta::Fp;
+
+    use crate::dev_tools::check_output;
+
+    #[derive(Debug, Clone)]
+    struct MyConfig<F: FieldExt> {
+        value: Column<Advice>,
+        out: Column<Advice>,
+        output: Column<Instance>,
+        q: Selector,
+        is_zero: IsZeroConfig<F>,
+    }
+
+    // materializes is_zero(value) into a cell and exposes it
+    #[derive(Default)]
+    struct MyCircuit<F> {
+        value: Value<F>,
+    }
+
+    impl<F: FieldExt> Circuit<F> for MyCircuit<F> {
+        type Config = MyConfig<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            let value = meta.advice_column();
+            let out = meta.advice_column();
+            let value_inv = meta.advice_column();
+            let output = meta.instance_column();
+            let q = meta.selector();
+            meta.enable_equality(out);
+            meta.enable_equality(output);
+
+            let is_zero = IsZeroChip::configure(
+                meta,
+                |meta| meta.query_selector(q),
+                |meta| meta.query_advice(value, Rotation::cur()),
+                value_inv,
+            );
+
+            meta.create_gate("is_zero out", |meta| {
+                let q = meta.query_selector(q);
+                let out = meta.query_advice(out, Rotation::cur());
+                vec![q * (out - is_zero.expr())]
+            });
+
+            MyConfig {
+                value,
+                out,
+                output,
+                q,
+                is_zero,
+            }
+        }
+
+        fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<F>) -> Result<(), Error> {
+            let chip = IsZeroChip::construct(config.is_zero.clone());
+
+            let out = layouter.assign_region(
+                || "is_zero",
+                |mut region| {
+                    config.q.enable(&mut region, 0)?;
+                    region.assign_advice(|| "value", config.value, 0, || self.value)?;
+                    chip.assign(&mut region, 0, self.value)?;
+                    region.assign_advice(|| "out", config.out, 0, || self.value.map(IsZeroChip::native))
+                },
+            )?;
+
+            layouter.constrain_instance(out.cell(), config.output, 0)
+        }
+    }
+
+    #[test]
+    fn test_is_zero_reference() {
+        check_output(
+            4,
+            0,
+            16,
+            // half of the values are 0
+            |rng| if rng.below(2) == 0 { Fp::zero() } else { rng.field() },
+            |value| IsZeroChip::native(*value),
+            |_, out| Fp::one() - out,
+            |value, out| (MyCircuit { value: Value::known(*value) }, vec![vec![*out]]),
+        );
+    }
+
+    #[test]
+    fn test_is_zero_forged_witness() {
+        use crate::dev_tools::{Forgeries, Rejection};
+
+        let forgeries = Forgeries::new(|| MyCircuit { value: Value::known(Fp::from(5)) }, vec![vec![Fp::zero()]]);
+
+        // value_inv = 0 claims that 5 is zero, column 2 is value_inv
+        forgeries
+            .forge("is_zero", 2, 0, Fp::zero())
+            .assert_rejected(Rejection::Gate("is_zero"));
+
+        // out = 1 with the honest value_inv, column 1 is out
+        forgeries
+            .forge("is_zero", 1, 0, Fp::one())
+            .assert_rejected(Rejection::Gate("is_zero out"));
+    }
+}
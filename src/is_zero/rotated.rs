@@ -0,0 +1,187 @@
+// Goal: an IsZero gadget whose value_inv lives at a configurable rotation from the row where the gate is enabled.
+// The value closure can query any row too, so we can test expressions across rows such as next - cur == 0.
+// That's useful in trace-style circuits to detect that the state didn't change between two consecutive rows.
+//
+// e.g. ROTATION = 1 and value = state(next) - state(cur)
+//   state | value_inv              | q_enable
+//   s_0   |                        | 1
+//   s_1   | 1 / (s_1 - s_0) or 0   | 1
+//   s_2   | 1 / (s_2 - s_1) or 0   | 0
+
+use halo2_proofs::{arithmetic::FieldExt, circuit::*, plonk::*, poly::Rotation};
+
+use super::{IsZeroConfig, IsZeroInstructions};
+
+#[derive(Debug, Clone)]
+pub struct RotatedIsZeroChip<F: FieldExt, const ROTATION: i32> {
+    config: IsZeroConfig<F>,
+}
+
+impl<F: FieldExt, const ROTATION: i32> IsZeroInstructions<F> for RotatedIsZeroChip<F, ROTATION> {
+    type Config = IsZeroConfig<F>;
+
+    fn construct(config: IsZeroConfig<F>) -> Self {
+        RotatedIsZeroChip { config }
+    }
+
+    fn configure(
+        meta: &mut ConstraintSystem<F>,
+        q_enable: impl FnOnce(&mut VirtualCells<'_, F>) -> Expression<F>,
+        value: impl FnOnce(&mut VirtualCells<'_, F>) -> Expression<F>,
+        value_inv: Column<Advice>,
+    ) -> IsZeroConfig<F> {
+        let mut is_zero_expr = Expression::Constant(F::zero());
+
+        meta.create_gate("rotated is_zero", |meta| {
+            let value = value(meta);
+            let q_enable = q_enable(meta);
+            // same as IsZeroChip, but value_inv is queried ROTATION rows away from the selector
+            let value_inv = meta.query_advice(value_inv, Rotation(ROTATION));
+
+            is_zero_expr = Expression::Constant(F::one()) - value.clone() * value_inv;
+            vec![q_enable * value * is_zero_expr.clone()]
+        });
+
+        IsZeroConfig {
+            value_inv,
+            is_zero_expr,
+        }
+    }
+
+    fn expr(config: &IsZeroConfig<F>) -> Expression<F> {
+        config.expr()
+    }
+
+    // offset is the row where the gate is enabled, value_inv is assigned at offset + ROTATION
+    fn assign(
+        &self,
+        region: &mut Region<'_, F>,
+        offset: usize,
+        value: Value<F>,
+    ) -> Result<(), Error> {
+        let row = offset as i32 + ROTATION;
+        assert!(row >= 0, "value_inv would be assigned before the start of the region");
+
+        let value_inv = value.map(|value| value.invert().unwrap_or(F::zero()));
+        region.assign_advice(|| "value inv", self.config.value_inv, row as usize, || value_inv)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use halo2_proofs::{dev::MockProver, pasta::Fp};
+
+    #[derive(Debug, Clone)]
+    struct TraceConfig<F: FieldExt> {
+        state: Column<Advice>,
+        unchanged: Column<Advice>,
+        q_enable: Selector,
+        state_unchanged: IsZeroConfig<F>,
+    }
+
+    // For every pair of consecutive rows of the trace, unchanged is 1 if the state is the same and 0 otherwise
+    #[derive(Default)]
+    struct TraceCircuit<F> {
+        trace: Vec<F>,
+        unchanged: Vec<F>,
+    }
+
+    impl<F: FieldExt> Circuit<F> for TraceCircuit<F> {
+        type Config = TraceConfig<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            let state = meta.advice_column();
+            let unchanged = meta.advice_column();
+            let value_inv = meta.advice_column();
+            let q_enable = meta.selector();
+
+            let state_unchanged = RotatedIsZeroChip::<F, 1>::configure(
+                meta,
+                |meta| meta.query_selector(q_enable),
+                |meta| meta.query_advice(state, Rotation::next()) - meta.query_advice(state, Rotation::cur()),
+                value_inv,
+            );
+
+            meta.create_gate("unchanged", |meta| {
+                let q_enable = meta.query_selector(q_enable);
+                let unchanged = meta.query_advice(unchanged, Rotation::cur());
+                vec![q_enable * (unchanged - state_unchanged.expr())]
+            });
+
+            TraceConfig {
+                state,
+                unchanged,
+                q_enable,
+                state_unchanged,
+            }
+        }
+
+        fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<F>) -> Result<(), Error> {
+            let chip = RotatedIsZeroChip::<F, 1>::construct(config.state_unchanged.clone());
+
+            layouter.assign_region(
+                || "trace",
+                |mut region| {
+                    for (row, state) in self.trace.iter().enumerate() {
+                        region.assign_advice(|| "state", config.state, row, || Value::known(*state))?;
+                    }
+                    for (row, unchanged) in self.unchanged.iter().enumerate() {
+                        config.q_enable.enable(&mut region, row)?;
+                        region.assign_advice(|| "unchanged", config.unchanged, row, || Value::known(*unchanged))?;
+                        chip.assign(&mut region, row, Value::known(self.trace[row + 1] - self.trace[row]))?;
+                    }
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    #[test]
+    fn test_rotated_is_zero() {
+        let k = 4;
+        let trace = [1, 1, 2, 2, 3].map(Fp::from).to_vec();
+
+        let circuit = TraceCircuit {
+            trace: trace.clone(),
+            unchanged: [1, 0, 1, 0].map(Fp::from).to_vec(),
+        };
+        let prover = MockProver::run(k, &circuit, vec![]).unwrap();
+        prover.assert_satisfied();
+        crate::dev_tools::assert_fully_constrained(k, &circuit, vec![]);
+
+        let circuit = TraceCircuit {
+            trace,
+            unchanged: [1, 1, 1, 0].map(Fp::from).to_vec(),
+        };
+        let prover = MockProver::run(k, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_rotated_is_zero_forged_witness() {
+        use crate::dev_tools::{Forgeries, Rejection};
+
+        let circuit = || TraceCircuit {
+            trace: [1, 1, 2, 2, 3].map(Fp::from).to_vec(),
+            unchanged: [1, 0, 1, 0].map(Fp::from).to_vec(),
+        };
+        let forgeries = Forgeries::new(circuit, vec![]);
+
+        // the state changes between rows 1 and 2, value_inv = 0 one row below claims it doesn't
+        forgeries
+            .forge("trace", 2, 2, Fp::zero())
+            .assert_rejected(Rejection::Gate("rotated is_zero"));
+
+        // a wrong unchanged flag, column 1 is unchanged
+        forgeries
+            .forge("trace", 1, 1, Fp::one())
+            .assert_rejected(Rejection::Gate("unchanged"));
+    }
+}
@@ -0,0 +1,286 @@
+// Goal: prove v < bound where bound is an assigned cell (private or public), not a compile-time constant like RANGE.
+// Both v and diff = bound - v - 1 are range checked to NUM_BYTES bytes with the lookup of ByteDecompositionChip.
+// If v < 2^n and diff < 2^n then bound = v + 1 + diff < 2^(n + 1) doesn't wrap around the modulus, so v < bound.
+// If v >= bound instead, diff wraps around to a huge field element and its decomposition fails.
+// v <= bound is the same check with diff = bound - v, which is what "amount <= balance" needs.
+// The chip is sound for any bound, but only complete when the gap is small enough: a true v < bound is still
+// rejected if bound - v - 1 (bound - v for <=) doesn't fit in NUM_BYTES bytes, e.g. any v against a bound of 2^(n + 1).
+// Range check the bound to NUM_BYTES bytes beforehand (e.g. ByteDecompositionChip::decompose) and every v < bound
+// gets through.
+//
+//   value | bound | diff          | q_lt | q_le
+//   v     | b     | b - v - 1     | 1    | 0
+//   v     | b     | b - v         | 0    | 1
+
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    circuit::{AssignedCell, Layouter},
+    plonk::{Advice, Column, ConstraintSystem, Error, Expression, Selector},
+    poly::Rotation,
+};
+
+use crate::byte_decomposition::{ByteDecompositionChip, ByteDecompositionConfig};
+
+#[derive(Debug, Clone)]
+pub struct LessThanConfig<F: FieldExt, const NUM_BYTES: usize> {
+    value: Column<Advice>,
+    bound: Column<Advice>,
+    diff: Column<Advice>,
+    q_lt: Selector,
+    q_le: Selector,
+    decomposition: ByteDecompositionConfig<F, NUM_BYTES>,
+}
+
+pub struct LessThanChip<F: FieldExt, const NUM_BYTES: usize> {
+    config: LessThanConfig<F, NUM_BYTES>,
+}
+
+impl<F: FieldExt, const NUM_BYTES: usize> LessThanChip<F, NUM_BYTES> {
+    pub fn construct(config: LessThanConfig<F, NUM_BYTES>) -> Self {
+        Self { config }
+    }
+
+    pub fn configure(meta: &mut ConstraintSystem<F>, advice: [Column<Advice>; 4]) -> LessThanConfig<F, NUM_BYTES> {
+        let [value, bound, diff, byte] = advice;
+        // n + 1 bits must not wrap around the modulus
+        assert!(8 * NUM_BYTES < F::NUM_BITS as usize);
+
+        meta.enable_equality(bound);
+        meta.enable_equality(diff);
+        // the decomposition also enables equality on value and byte
+        let decomposition = ByteDecompositionChip::configure(meta, value, byte);
+
+        let q_lt = meta.selector();
+        let q_le = meta.selector();
+
+        meta.create_gate("less than", |meta| {
+            let q_lt = meta.query_selector(q_lt);
+            let q_le = meta.query_selector(q_le);
+            let value = meta.query_advice(value, Rotation::cur());
+            let bound = meta.query_advice(bound, Rotation::cur());
+            let diff = meta.query_advice(diff, Rotation::cur());
+            let one = Expression::Constant(F::one());
+            vec![
+                ("diff = bound - v - 1", q_lt * (diff.clone() - (bound.clone() - value.clone() - one))),
+                ("diff = bound - v", q_le * (diff - (bound - value))),
+            ]
+        });
+
+        LessThanConfig {
+            value,
+            bound,
+            diff,
+            q_lt,
+            q_le,
+            decomposition,
+        }
+    }
+
+    // Native reference of what the chip accepts: value and diff = bound - value - 1 (bound - value when not strict)
+    // both fit in NUM_BYTES bytes
+    pub fn native(value: u64, bound: u64, strict: bool) -> bool {
+        let fits = |x: u128| x.checked_shr(8 * NUM_BYTES as u32).unwrap_or(0) == 0;
+        let diff = bound as i128 - value as i128 - strict as i128;
+        fits(value as u128) && diff >= 0 && fits(diff as u128)
+    }
+
+    // The byte range check table must be loaded once per circuit
+    pub fn load_table(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
+        ByteDecompositionChip::construct(self.config.decomposition.clone()).load_table(layouter)
+    }
+
+    // Constrains value < bound, value must fit in NUM_BYTES bytes.
+    // An honest value is only accepted if bound - value - 1 fits in NUM_BYTES bytes too, see the header.
+    pub fn assert_less_than(
+        &self,
+        layouter: impl Layouter<F>,
+        value: &AssignedCell<F, F>,
+        bound: &AssignedCell<F, F>,
+    ) -> Result<(), Error> {
+        self.assign(layouter, value, bound, true)
+    }
+
+    // Constrains value <= bound, value must fit in NUM_BYTES bytes.
+    // An honest value is only accepted if bound - value fits in NUM_BYTES bytes too, see the header.
+    pub fn assert_less_than_or_equal(
+        &self,
+        layouter: impl Layouter<F>,
+        value: &AssignedCell<F, F>,
+        bound: &AssignedCell<F, F>,
+    ) -> Result<(), Error> {
+        self.assign(layouter, value, bound, false)
+    }
+
+    fn assign(
+        &self,
+        mut layouter: impl Layouter<F>,
+        value: &AssignedCell<F, F>,
+        bound: &AssignedCell<F, F>,
+        strict: bool,
+    ) -> Result<(), Error> {
+        let decomposition = ByteDecompositionChip::construct(self.config.decomposition.clone());
+
+        let diff = layouter.assign_region(
+            || if strict { "less than" } else { "less than or equal" },
+            |mut region| {
+                if strict {
+                    self.config.q_lt.enable(&mut region, 0)?;
+                } else {
+                    self.config.q_le.enable(&mut region, 0)?;
+                }
+
+                value.copy_advice(|| "value", &mut region, self.config.value, 0)?;
+                bound.copy_advice(|| "bound", &mut region, self.config.bound, 0)?;
+                let diff = bound.value().zip(value.value()).map(|(bound, value)| {
+                    let diff = *bound - value;
+                    if strict {
+                        diff - F::one()
+                    } else {
+                        diff
+                    }
+                });
+                region.assign_advice(|| "diff", self.config.diff, 0, || diff)
+            },
+        )?;
+
+        // both decompositions only succeed if the values fit in NUM_BYTES bytes
+        decomposition.decompose(layouter.namespace(|| "range check value"), value)?;
+        decomposition.decompose(layouter.namespace(|| "range check diff"), &diff)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use halo2_proofs::{
+        circuit::{SimpleFloorPlanner, Value},
+        pasta::Fp,
+        plonk::{Circuit, Instance},
+    };
+
+    use crate::dev_tools::{min_k, mock_prove};
+
+    #[derive(Debug, Clone)]
+    struct MyConfig {
+        input: Column<Advice>,
+        balance: Column<Instance>,
+        less_than: LessThanConfig<Fp, 2>,
+    }
+
+    // proves amount < limit and amount <= balance, where the limit is private and the balance is public
+    #[derive(Default)]
+    struct MyCircuit {
+        amount: u64,
+        limit: u64,
+    }
+
+    impl Circuit<Fp> for MyCircuit {
+        type Config = MyConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            let advice = [
+                meta.advice_column(),
+                meta.advice_column(),
+                meta.advice_column(),
+                meta.advice_column(),
+            ];
+            let balance = meta.instance_column();
+            meta.enable_equality(balance);
+
+            MyConfig {
+                input: advice[1],
+                balance,
+                less_than: LessThanChip::configure(meta, advice),
+            }
+        }
+
+        fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<Fp>) -> Result<(), Error> {
+            let chip = LessThanChip::construct(config.less_than);
+            chip.load_table(&mut layouter)?;
+
+            let (amount, limit, balance) = layouter.assign_region(
+                || "load inputs",
+                |mut region| {
+                    let amount = region.assign_advice(|| "amount", config.input, 0, || Value::known(Fp::from(self.amount)))?;
+                    let limit = region.assign_advice(|| "limit", config.input, 1, || Value::known(Fp::from(self.limit)))?;
+                    let balance = region.assign_advice_from_instance(|| "balance", config.balance, 0, config.input, 2)?;
+                    Ok((amount, limit, balance))
+                },
+            )?;
+
+            chip.assert_less_than(layouter.namespace(|| "amount < limit"), &amount, &limit)?;
+            chip.assert_less_than_or_equal(layouter.namespace(|| "amount <= balance"), &amount, &balance)
+        }
+    }
+
+    #[test]
+    fn test_less_than() {
+        let run = |amount: u64, limit: u64, balance: u64| {
+            let circuit = MyCircuit { amount, limit };
+            mock_prove(&circuit, vec![vec![Fp::from(balance)]]).unwrap()
+        };
+
+        run(5, 10, 7).assert_satisfied();
+        run(10, 11, 10).assert_satisfied();
+        run(0, 1, 0).assert_satisfied();
+        run(65534, 65535, 65535).assert_satisfied();
+        let k = min_k(&MyCircuit { amount: 5, limit: 10 }, &[vec![Fp::from(7)]]).unwrap();
+        crate::dev_tools::assert_fully_constrained(k, &MyCircuit { amount: 5, limit: 10 }, vec![vec![Fp::from(7)]]);
+
+        // amount == limit
+        assert!(run(10, 10, 20).verify().is_err());
+        // amount > balance
+        assert!(run(10, 20, 9).verify().is_err());
+        // amount doesn't fit in 2 bytes
+        assert!(run(1 << 16, 1 << 17, 1 << 17).verify().is_err());
+        // 0 < 2^17 is true but the gap doesn't fit in 2 bytes either, the limit is too large to be complete
+        assert!(!LessThanChip::<Fp, 2>::native(0, 1 << 17, true));
+        assert!(run(0, 1 << 17, 0).verify().is_err());
+    }
+
+    #[test]
+    fn test_less_than_reference() {
+        use crate::dev_tools::{check_acceptance, XorShift};
+
+        type Chip = LessThanChip<Fp, 2>;
+        check_acceptance(
+            9,
+            0,
+            16,
+            // amounts up to 17 bits, the limit and the balance are often right around the amount
+            |rng| {
+                let amount = rng.below(1 << 17) as u64 >> rng.below(2);
+                let near = |rng: &mut XorShift| match rng.below(2) {
+                    0 => (amount + rng.below(3) as u64).saturating_sub(1),
+                    _ => rng.below(1 << 17) as u64,
+                };
+                (amount, near(rng), near(rng))
+            },
+            |&(amount, limit, balance)| Chip::native(amount, limit, true) && Chip::native(amount, balance, false),
+            |&(amount, limit, balance)| (MyCircuit { amount, limit }, vec![vec![Fp::from(balance)]]),
+        );
+    }
+
+    #[test]
+    fn test_less_than_forged_witness() {
+        use crate::dev_tools::{Forgeries, Rejection};
+
+        let forgeries = Forgeries::new(|| MyCircuit { amount: 5, limit: 10 }, vec![vec![Fp::from(7)]]);
+
+        // diff = limit - amount - 1 = 4, column 2 is diff
+        forgeries
+            .forge("less than", 2, 0, Fp::from(3))
+            .assert_rejected(Rejection::Gate("less than"));
+
+        // a smaller amount in the comparison than the one loaded, column 0 is value
+        forgeries
+            .forge("less than or equal", 0, 0, Fp::from(1))
+            .assert_rejected(Rejection::Permutation);
+    }
+}
@@ -1,3 +1,17 @@
 mod is_zero;
 mod example3;
-mod range_check;
\ No newline at end of file
+mod range_check;
+mod table_registry;
+mod bitwise;
+mod byte_decomposition;
+mod bit_decomposition;
+mod boolean;
+mod arithmetic;
+mod inverse;
+mod set_membership;
+mod function_lookup;
+mod bit_length;
+mod less_than;
+// only the tests of the chips use the dev tools, they're not part of the library
+#[cfg(test)]
+mod dev_tools;
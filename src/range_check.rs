@@ -1,3 +1,46 @@
 mod example4;
-mod example5;
-mod example6;
\ No newline at end of file
+pub(crate) mod example5;
+mod example6;
+
+// the production range check, the examples above are demos on top of it
+mod chip;
+pub(crate) mod table;
+pub use chip::{RangeCheckChip, RangeCheckConfig, RangeCheckMode, RangeConstrained};
+
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    circuit::{AssignedCell, Layouter, Value},
+    plonk::{Advice, Assigned, Column, ConstraintSystem, Error, Expression},
+};
+
+// The instructions of a range check, so that callers don't depend on a specific implementation
+// (e.g. the range-check expression, the lookup or the tagged lookup of RangeCheckChip)
+pub trait RangeCheckInstructions<F: FieldExt>: Sized {
+    type Config: Clone + std::fmt::Debug;
+
+    fn construct(config: Self::Config) -> Self;
+
+    // value is the column of the checked values, the implementation allocates any other column it needs
+    fn configure(meta: &mut ConstraintSystem<F>, value: Column<Advice>) -> Self::Config;
+
+    // Assign `value` and constrain it to be in [0, range)
+    fn range_check(
+        &self,
+        layouter: impl Layouter<F>,
+        value: Value<Assigned<F>>,
+        range: usize,
+    ) -> Result<AssignedCell<Assigned<F>, F>, Error>;
+
+    // Load the lookup tables used by the range check, if any.
+    // This must be called once per circuit
+    fn load(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error>;
+}
+
+// For a value v and a range R, check that v < R
+// (0 - v) * (1 - v) * (2 - v) * ... * (R - 1 - v) = 0 if v is any of these values!
+// The degree of the expression is R, so this is only fit for small ranges
+pub fn range_check_expr<F: FieldExt>(range: usize, value: Expression<F>) -> Expression<F> {
+    (0..range).fold(Expression::Constant(F::one()), |expr, i| {
+        expr * (Expression::Constant(F::from(i as u64)) - value.clone())
+    })
+}
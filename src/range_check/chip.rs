@@ -0,0 +1,609 @@
+// Goal: a single range check chip with the three modes that example4, example5 and example6 built one after the other.
+//  - Polynomial: v < RANGE with the range-check expression, cheap for a small RANGE
+//  - Lookup: v < LOOKUP_RANGE with a lookup into the value column of the tagged table
+//  - TaggedLookup: v < 2^k, the exact bit length b of v is looked up together with v and k - b is looked up in the
+//    value column of the same table, so 0 <= k - b < LOOKUP_RANGE.
+//    k is either fixed when the circuit is built or an assigned cell, e.g. copied from an instance column.
+//    A fixed k is at most LOOKUP_NUMBITS, a public one can be larger: b is at most LOOKUP_NUMBITS so any k up to
+//    LOOKUP_RANGE passes for every v < 2^k, that's already more bits than a field element has for an 8-bit table.
+// All the modes share the same columns and return a RangeConstrained value.
+//
+//   value  | num_bits | bound | q_range_check | q_lookup | q_tagged_lookup
+//   v      |          |       | 1             | 0        | 0
+//   v'     |          |       | 0             | 1        | 0
+//   v''    | b        | k     | 0             | 0        | 1
+
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    circuit::{AssignedCell, Layouter, Value},
+    plonk::{Advice, Assigned, Column, ConstraintSystem, Constraints, Error, Expression, Fixed, Selector},
+    poly::Rotation,
+};
+
+use super::{range_check_expr, table::RangeCheckTable, RangeCheckInstructions};
+use crate::bit_decomposition::to_le_bits;
+
+/// A range-constrained value in the circuit produced by the RangeCheckChip.
+#[derive(Debug, Clone)]
+pub struct RangeConstrained<F: FieldExt>(pub AssignedCell<Assigned<F>, F>);
+
+// How a value is range checked
+#[derive(Debug, Clone, Copy)]
+pub enum RangeCheckMode<'a, F: FieldExt> {
+    // v < RANGE
+    Polynomial,
+    // v < LOOKUP_RANGE
+    Lookup,
+    // v < 2^num_bits, with 1 <= num_bits <= LOOKUP_NUMBITS
+    TaggedLookup { num_bits: usize },
+    // v < 2^k where k is an assigned cell, any k from the bit length of v to LOOKUP_RANGE passes
+    TaggedLookupWithBound(&'a AssignedCell<F, F>),
+}
+
+#[derive(Debug, Clone)]
+pub struct RangeCheckConfig<F: FieldExt, const RANGE: usize, const LOOKUP_NUMBITS: usize, const LOOKUP_RANGE: usize> {
+    value: Column<Advice>,
+    num_bits: Column<Advice>,
+    bound: Column<Advice>,
+    q_range_check: Selector,
+    q_lookup: Selector,
+    q_tagged_lookup: Selector,
+    table: RangeCheckTable<F, LOOKUP_NUMBITS, LOOKUP_RANGE>,
+}
+
+pub struct RangeCheckChip<F: FieldExt, const RANGE: usize, const LOOKUP_NUMBITS: usize, const LOOKUP_RANGE: usize> {
+    config: RangeCheckConfig<F, RANGE, LOOKUP_NUMBITS, LOOKUP_RANGE>,
+}
+
+impl<F: FieldExt, const RANGE: usize, const LOOKUP_NUMBITS: usize, const LOOKUP_RANGE: usize>
+    RangeCheckChip<F, RANGE, LOOKUP_NUMBITS, LOOKUP_RANGE>
+{
+    pub fn construct(config: RangeCheckConfig<F, RANGE, LOOKUP_NUMBITS, LOOKUP_RANGE>) -> Self {
+        Self { config }
+    }
+
+    pub fn configure(
+        meta: &mut ConstraintSystem<F>,
+        value: Column<Advice>,
+        num_bits: Column<Advice>,
+        bound: Column<Advice>,
+        constant: Column<Fixed>,
+    ) -> RangeCheckConfig<F, RANGE, LOOKUP_NUMBITS, LOOKUP_RANGE> {
+        meta.enable_equality(value);
+        // the bound is either a constant or copied from another cell
+        meta.enable_equality(bound);
+        meta.enable_constant(constant);
+
+        let q_range_check = meta.selector();
+        // Simple selectors cannot appear in lookup arguments
+        let q_lookup = meta.complex_selector();
+        let q_tagged_lookup = meta.complex_selector();
+        let table = RangeCheckTable::configure(meta);
+
+        meta.create_gate("range check", |meta| {
+            let q_range_check = meta.query_selector(q_range_check);
+            let value = meta.query_advice(value, Rotation::cur());
+            Constraints::with_selector(q_range_check, [("range check", range_check_expr(RANGE, value))])
+        });
+
+        meta.lookup(|meta| {
+            let q_lookup = meta.query_selector(q_lookup);
+            let value = meta.query_advice(value, Rotation::cur());
+            vec![(q_lookup * value, table.value)]
+        });
+
+        // (0, 0) is not a row of the table because 0 takes 1 bit, so disabled rows look up (0, 1) instead
+        meta.lookup(|meta| {
+            let q_tagged_lookup = meta.query_selector(q_tagged_lookup);
+            let not_q_tagged_lookup = Expression::Constant(F::one()) - q_tagged_lookup.clone();
+            let value = meta.query_advice(value, Rotation::cur());
+            let num_bits = meta.query_advice(num_bits, Rotation::cur());
+            vec![
+                (q_tagged_lookup.clone() * value, table.value),
+                (q_tagged_lookup * num_bits + not_q_tagged_lookup, table.num_bits),
+            ]
+        });
+
+        // num_bits is the exact bit length of the value, so value < 2^num_bits <= 2^bound
+        // if bound - num_bits is in [0, LOOKUP_RANGE). A range-check expression of bound - num_bits would cap the bound
+        // at 2 * LOOKUP_NUMBITS, the value column of the table takes any bound that makes sense for the field.
+        // Disabled rows look up 0, which is in the table.
+        meta.lookup(|meta| {
+            let q_tagged_lookup = meta.query_selector(q_tagged_lookup);
+            let num_bits = meta.query_advice(num_bits, Rotation::cur());
+            let bound = meta.query_advice(bound, Rotation::cur());
+            vec![(q_tagged_lookup * (bound - num_bits), table.value)]
+        });
+
+        RangeCheckConfig {
+            value,
+            num_bits,
+            bound,
+            q_range_check,
+            q_lookup,
+            q_tagged_lookup,
+            table,
+        }
+    }
+
+    // Native reference of the range check: every mode checks that the canonical integer of value is smaller than range,
+    // with range = RANGE, LOOKUP_RANGE, 2^num_bits or 2^k depending on the mode
+    pub fn native(value: &Assigned<F>, range: u64) -> bool {
+        num_bits(value) <= 64 && (value.evaluate().get_lower_128() as u64) < range
+    }
+
+    // The lookup table must be loaded once per circuit, even if only the polynomial mode is used
+    pub fn load_table(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
+        self.config.table.load(layouter)
+    }
+
+    // Assign value and range check it with the given mode
+    pub fn assign(
+        &self,
+        mut layouter: impl Layouter<F>,
+        value: Value<Assigned<F>>,
+        mode: RangeCheckMode<'_, F>,
+    ) -> Result<RangeConstrained<F>, Error> {
+        let name = match mode {
+            RangeCheckMode::Polynomial => "Assign value",
+            RangeCheckMode::Lookup => "Assign value for lookup range check",
+            RangeCheckMode::TaggedLookup { .. } | RangeCheckMode::TaggedLookupWithBound(_) => {
+                "Assign value for tagged lookup range check"
+            }
+        };
+
+        layouter.assign_region(
+            || name,
+            |mut region| {
+                let offset = 0;
+
+                match mode {
+                    RangeCheckMode::Polynomial => self.config.q_range_check.enable(&mut region, offset)?,
+                    RangeCheckMode::Lookup => self.config.q_lookup.enable(&mut region, offset)?,
+                    RangeCheckMode::TaggedLookup { num_bits } => {
+                        assert!((1..=LOOKUP_NUMBITS).contains(&num_bits));
+                        self.config.q_tagged_lookup.enable(&mut region, offset)?;
+                        region.assign_advice_from_constant(
+                            || "bound",
+                            self.config.bound,
+                            offset,
+                            F::from(num_bits as u64),
+                        )?;
+                    }
+                    RangeCheckMode::TaggedLookupWithBound(bound) => {
+                        self.config.q_tagged_lookup.enable(&mut region, offset)?;
+                        bound.copy_advice(|| "bound", &mut region, self.config.bound, offset)?;
+                    }
+                }
+
+                if let RangeCheckMode::TaggedLookup { .. } | RangeCheckMode::TaggedLookupWithBound(_) = mode {
+                    // the exact bit length stays private
+                    let value_num_bits = value.map(|value| F::from(num_bits(&value) as u64));
+                    region.assign_advice(|| "num_bits", self.config.num_bits, offset, || value_num_bits)?;
+                }
+
+                region
+                    .assign_advice(|| "value", self.config.value, offset, || value)
+                    .map(RangeConstrained)
+            },
+        )
+    }
+}
+
+// the exact bit length of a value, as in the table 0 takes 1 bit
+pub(crate) fn num_bits<F: FieldExt>(value: &Assigned<F>) -> usize {
+    to_le_bits(&value.evaluate())
+        .iter()
+        .rposition(|bit| *bit)
+        .map_or(1, |i| i + 1)
+}
+
+impl<F: FieldExt, const RANGE: usize, const LOOKUP_NUMBITS: usize, const LOOKUP_RANGE: usize> RangeCheckInstructions<F>
+    for RangeCheckChip<F, RANGE, LOOKUP_NUMBITS, LOOKUP_RANGE>
+{
+    type Config = RangeCheckConfig<F, RANGE, LOOKUP_NUMBITS, LOOKUP_RANGE>;
+
+    fn construct(config: Self::Config) -> Self {
+        RangeCheckChip::construct(config)
+    }
+
+    // the num_bits and bound columns of the tagged lookup, and the fixed column of its constant bounds
+    fn configure(meta: &mut ConstraintSystem<F>, value: Column<Advice>) -> Self::Config {
+        let num_bits = meta.advice_column();
+        let bound = meta.advice_column();
+        let constant = meta.fixed_column();
+        RangeCheckChip::configure(meta, value, num_bits, bound, constant)
+    }
+
+    // RANGE uses the polynomial mode, LOOKUP_RANGE the lookup and smaller powers of two the tagged lookup
+    fn range_check(
+        &self,
+        layouter: impl Layouter<F>,
+        value: Value<Assigned<F>>,
+        range: usize,
+    ) -> Result<AssignedCell<Assigned<F>, F>, Error> {
+        let mode = if range == RANGE {
+            RangeCheckMode::Polynomial
+        } else if range == LOOKUP_RANGE {
+            RangeCheckMode::Lookup
+        } else if range.is_power_of_two() && range > 1 && range < LOOKUP_RANGE {
+            RangeCheckMode::TaggedLookup {
+                num_bits: range.trailing_zeros() as usize,
+            }
+        } else {
+            panic!("range {} is not supported by the range check chip", range);
+        };
+        self.assign(layouter, value, mode).map(|value| value.0)
+    }
+
+    fn load(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
+        self.load_table(layouter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use halo2_proofs::{
+        circuit::SimpleFloorPlanner,
+        pasta::Fp,
+        plonk::{Circuit, Instance},
+    };
+
+    use crate::dev_tools::{format_value, Failure, FailureKind};
+
+    // Chip-level message of a failure of the range check gates and lookups of RangeCheckChip<Fp, 8, 8, 256>,
+    // for dev_tools::diagnose
+    fn explain(failure: &Failure<Fp>) -> Option<String> {
+        let region = failure.region_name();
+        match &failure.kind {
+            FailureKind::Gate { gate, .. } if gate == "range check" => Some(format!(
+                "value {} in region '{}' is outside range [0, 8)",
+                format_value(failure.cell("value")?),
+                region
+            )),
+            // lookup 2 checks bound - num_bits against the value column of the same table
+            FailureKind::Lookup { index: 2, .. } => Some(format!(
+                "bit length {} in region '{}' is not in [bound - 255, bound] for bound {}",
+                format_value(failure.cell("num_bits")?),
+                region,
+                format_value(failure.cell("bound")?)
+            )),
+            // the inputs are (value) for the lookup mode and (value, num_bits) for the tagged lookup
+            FailureKind::Lookup { table: Some(table), .. } if table == "load range-check table" => match failure.inputs[..] {
+                [value] => Some(format!(
+                    "value {} in region '{}' is outside range [0, 256)",
+                    format_value(value),
+                    region
+                )),
+                [value, num_bits] => Some(format!(
+                    "lookup for (num_bits={}, value={}) not found in range-check table",
+                    format_value(num_bits),
+                    format_value(value)
+                )),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    #[derive(Debug, Clone)]
+    struct MyConfig {
+        instance: Column<Instance>,
+        range_check: RangeCheckConfig<Fp, 8, 8, 256>,
+    }
+
+    // range checks `value` with every mode, the bound of the last one is public
+    #[derive(Default)]
+    struct MyCircuit {
+        value: Value<Assigned<Fp>>,
+        num_bits: usize,
+    }
+
+    impl Circuit<Fp> for MyCircuit {
+        type Config = MyConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self {
+                value: Value::unknown(),
+                num_bits: self.num_bits,
+            }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            let value = meta.advice_column();
+            let num_bits = meta.advice_column();
+            let bound = meta.advice_column();
+            let constant = meta.fixed_column();
+            let instance = meta.instance_column();
+            meta.enable_equality(instance);
+
+            MyConfig {
+                instance,
+                range_check: RangeCheckChip::configure(meta, value, num_bits, bound, constant),
+            }
+        }
+
+        fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<Fp>) -> Result<(), Error> {
+            let chip = RangeCheckChip::construct(config.range_check.clone());
+            chip.load_table(&mut layouter)?;
+
+            let bound = layouter.assign_region(
+                || "load public bound",
+                |mut region| region.assign_advice_from_instance(|| "bound", config.instance, 0, config.range_check.bound, 0),
+            )?;
+
+            chip.assign(layouter.namespace(|| "polynomial"), self.value, RangeCheckMode::Polynomial)?;
+            chip.assign(layouter.namespace(|| "lookup"), self.value, RangeCheckMode::Lookup)?;
+            chip.assign(
+                layouter.namespace(|| "tagged lookup"),
+                self.value,
+                RangeCheckMode::TaggedLookup { num_bits: self.num_bits },
+            )?;
+            chip.assign(
+                layouter.namespace(|| "tagged lookup with bound"),
+                self.value,
+                RangeCheckMode::TaggedLookupWithBound(&bound),
+            )?;
+            Ok(())
+        }
+    }
+
+    #[derive(Debug, Clone, Copy)]
+    enum TestMode {
+        Polynomial,
+        Lookup,
+        Tagged(usize),
+        WithBound,
+    }
+
+    // range checks `value` with a single mode, the bound of WithBound is public
+    struct ModeCircuit {
+        value: Value<Assigned<Fp>>,
+        mode: TestMode,
+    }
+
+    impl Circuit<Fp> for ModeCircuit {
+        type Config = MyConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self {
+                value: Value::unknown(),
+                mode: self.mode,
+            }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            MyCircuit::configure(meta)
+        }
+
+        fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<Fp>) -> Result<(), Error> {
+            let chip = RangeCheckChip::construct(config.range_check.clone());
+            chip.load_table(&mut layouter)?;
+
+            let bound = layouter.assign_region(
+                || "load public bound",
+                |mut region| region.assign_advice_from_instance(|| "bound", config.instance, 0, config.range_check.bound, 0),
+            )?;
+
+            let mode = match self.mode {
+                TestMode::Polynomial => RangeCheckMode::Polynomial,
+                TestMode::Lookup => RangeCheckMode::Lookup,
+                TestMode::Tagged(num_bits) => RangeCheckMode::TaggedLookup { num_bits },
+                TestMode::WithBound => RangeCheckMode::TaggedLookupWithBound(&bound),
+            };
+            chip.assign(layouter.namespace(|| "range check"), self.value, mode)?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_range_check_chip() {
+        use crate::dev_tools::{min_k, mock_prove};
+
+        let run = |value: u64, num_bits: usize, bound: u64| {
+            let circuit = MyCircuit {
+                value: Value::known(Fp::from(value).into()),
+                num_bits,
+            };
+            mock_prove(&circuit, vec![vec![Fp::from(bound)]]).unwrap()
+        };
+
+        for value in 0..8 {
+            run(value, 3, 3).assert_satisfied();
+        }
+        run(5, 3, 8).assert_satisfied();
+        let circuit = MyCircuit {
+            value: Value::known(Fp::from(5).into()),
+            num_bits: 3,
+        };
+        let k = min_k(&circuit, &[vec![Fp::from(8)]]).unwrap();
+        crate::dev_tools::assert_fully_constrained(k, &circuit, vec![vec![Fp::from(8)]]);
+
+        // 8 is out of the polynomial range
+        assert!(run(8, 4, 4).verify().is_err());
+        // 7 doesn't fit in 2 bits
+        assert!(run(7, 2, 3).verify().is_err());
+        // 7 doesn't fit in 2 public bits
+        assert!(run(7, 3, 2).verify().is_err());
+        // a public bound above the 8 bits of the table, both for a small value and for the largest one
+        run(1, 3, 20).assert_satisfied();
+        run(7, 3, 64).assert_satisfied();
+    }
+
+    #[test]
+    fn test_range_check_chip_forged_witness() {
+        use crate::dev_tools::{Forgeries, Rejection};
+
+        let circuit = || MyCircuit {
+            value: Value::known(Fp::from(5).into()),
+            num_bits: 3,
+        };
+        let forgeries = Forgeries::new(circuit, vec![vec![Fp::from(8)]]);
+
+        // out-of-range values, column 0 is value
+        forgeries
+            .forge("Assign value", 0, 0, Fp::from(8))
+            .assert_rejected(Rejection::Gate("range check"));
+        forgeries
+            .forge("Assign value for lookup range check", 0, 0, Fp::from(256))
+            .assert_rejected(Rejection::Lookup(0));
+        // the first tagged region has the constant bound, the second one the public bound
+        forgeries
+            .forge_nth("Assign value for tagged lookup range check", 0, 0, 0, Fp::from(300))
+            .assert_rejected(Rejection::Lookup(1));
+
+        // a wrong bit length, column 1 is num_bits
+        forgeries
+            .forge_nth("Assign value for tagged lookup range check", 1, 1, 0, Fp::from(2))
+            .assert_rejected(Rejection::Lookup(1));
+
+        // a looser bound than the constant 3 or the public 8, column 2 is bound
+        forgeries
+            .forge_nth("Assign value for tagged lookup range check", 0, 2, 0, Fp::from(8))
+            .assert_rejected(Rejection::Permutation);
+        forgeries
+            .forge_nth("Assign value for tagged lookup range check", 1, 2, 0, Fp::from(9))
+            .assert_rejected(Rejection::Permutation);
+    }
+
+    #[test]
+    fn test_range_check_chip_diagnostics() {
+        use crate::dev_tools::{diagnose, min_k, Tampered};
+
+        let circuit = |value: u64| MyCircuit {
+            value: Value::known(Fp::from(value).into()),
+            num_bits: 4,
+        };
+        let k = min_k(&circuit(5), &[vec![Fp::from(8)]]).unwrap();
+        assert_eq!(diagnose(k, &circuit(5), vec![vec![Fp::from(8)]], explain), Ok(()));
+
+        // 8 only fails the polynomial mode
+        let diagnostics = diagnose(k, &circuit(8), vec![vec![Fp::from(8)]], explain).unwrap_err();
+        let messages: Vec<&str> = diagnostics.iter().map(|diagnostic| diagnostic.message.as_str()).collect();
+        assert_eq!(messages, vec!["value 0x8 in region 'Assign value' is outside range [0, 8)"]);
+
+        // 300 with a forged bit length of 4 in the tagged lookups, the first one also has a bound of 3 bits
+        let forged = Tampered::new(MyCircuit {
+            value: Value::known(Fp::from(5).into()),
+            num_bits: 3,
+        })
+        .forge_nth("Assign value for tagged lookup range check", 0, 0, 0, Fp::from(300))
+        .forge_nth("Assign value for tagged lookup range check", 0, 1, 0, Fp::from(4))
+        .forge_nth("Assign value for tagged lookup range check", 1, 0, 0, Fp::from(300))
+        .forge_nth("Assign value for tagged lookup range check", 1, 1, 0, Fp::from(4));
+        let diagnostics = diagnose(k, &forged, vec![vec![Fp::from(8)]], explain).unwrap_err();
+        let messages: Vec<&str> = diagnostics.iter().map(|diagnostic| diagnostic.message.as_str()).collect();
+        assert!(
+            messages.contains(&"lookup for (num_bits=0x4, value=0x12c) not found in range-check table"),
+            "{:#?}",
+            messages
+        );
+        assert!(
+            messages.contains(
+                &"bit length 0x4 in region 'Assign value for tagged lookup range check' is not in [bound - 255, bound] for bound 0x3"
+            ),
+            "{:#?}",
+            messages
+        );
+    }
+
+    #[test]
+    fn test_range_check_chip_describe() {
+        let circuit = MyCircuit {
+            value: Value::unknown(),
+            num_bits: 3,
+        };
+        let k = crate::dev_tools::min_k(&circuit, &[vec![Fp::from(8)]]).unwrap();
+        let description = crate::dev_tools::describe(k, &circuit).unwrap();
+
+        // (0 - value) * ... * (7 - value) enabled by q_range_check
+        let range_check = &description.gates[0].constraints[0];
+        assert_eq!(range_check.name, "range check");
+        assert_eq!(range_check.degree, 9);
+        assert!(range_check.polynomial.starts_with("s0 * (1 * (0 - value) * (1 - value)"), "{}", description);
+        assert!(range_check.polynomial.ends_with("* (7 - value))"), "{}", description);
+
+        // the disabled rows of the tagged lookup look up (0, 1)
+        assert_eq!(description.lookups[0].inputs, vec!["s1 * value"]);
+        assert_eq!(description.lookups[1].inputs, vec!["s2 * value", "s2 * num_bits + (1 - s2)"]);
+        assert_eq!(description.lookups[1].table, vec!["assign_value", "assign_num_bits"]);
+    }
+
+    #[test]
+    fn test_range_check_chip_fuzz() {
+        use crate::dev_tools::{fuzz, min_k, FuzzOptions};
+
+        let circuit = MyCircuit {
+            value: Value::known(Fp::from(5).into()),
+            num_bits: 3,
+        };
+        let options = FuzzOptions {
+            seed: 1,
+            iterations: 32,
+            max_cells: 1,
+        };
+        let k = min_k(&circuit, &[vec![Fp::from(8)]]).unwrap();
+        let report = fuzz(k, circuit, vec![vec![Fp::from(8)]], &options);
+        assert_eq!(report.skipped, 0, "{}", report);
+        assert!(report.rejected > 0, "{}", report);
+
+        // the values are free inputs, but only within their range: 256 for the lookup, 8 for the others
+        for mutations in &report.survivors {
+            for mutation in mutations {
+                assert_eq!(mutation.annotation, "value", "{}", report);
+                let range = if mutation.region == "Assign value for lookup range check" { 256 } else { 8 };
+                assert!((0..range).any(|i| mutation.value == Fp::from(i)), "{}", report);
+            }
+        }
+    }
+
+    #[test]
+    fn test_range_check_chip_reference() {
+        use crate::dev_tools::{check_acceptance, XorShift};
+
+        // every mode gets its own cases, the tagged lookup also picks its number of bits
+        let modes: [fn(&mut XorShift) -> TestMode; 4] = [
+            |_| TestMode::Polynomial,
+            |_| TestMode::Lookup,
+            |rng| TestMode::Tagged(1 + rng.below(8)),
+            |_| TestMode::WithBound,
+        ];
+        for (seed, mode) in modes.iter().enumerate() {
+            check_acceptance(
+                9,
+                seed as u64,
+                8,
+                |rng| {
+                    // small values, values around the lookup range and values spread over the field
+                    let value: Fp = match rng.below(3) {
+                        0 => Fp::from(rng.below(16) as u64),
+                        1 => Fp::from(rng.below(512) as u64),
+                        _ => rng.field(),
+                    };
+                    // the public bound, only used by WithBound. It can be larger than the 8 bits of the table
+                    let k = 1 + rng.below(16) as u64;
+                    (value, mode(rng), k)
+                },
+                |&(value, mode, k)| {
+                    let range = match mode {
+                        TestMode::Polynomial => 8,
+                        TestMode::Lookup => 256,
+                        TestMode::Tagged(num_bits) => 1 << num_bits,
+                        TestMode::WithBound => 1 << k,
+                    };
+                    RangeCheckChip::<Fp, 8, 8, 256>::native(&value.into(), range)
+                },
+                |&(value, mode, k)| {
+                    let circuit = ModeCircuit {
+                        value: Value::known(value.into()),
+                        mode,
+                    };
+                    (circuit, vec![vec![Fp::from(k)]])
+                },
+            );
+        }
+    }
+}
@@ -8,186 +8,52 @@
 //   value  | q_range_check | q_lookup | table_value
 //   v      | 1             |  0       |   0 
 //   v'     | 0             |  1       |   1
+// Both modes now live in RangeCheckChip (see chip.rs), this example uses its polynomial and lookup modes
+// through RangeCheckInstructions: the circuit only asks for a range and the chip picks the expression or the lookup
 
-use std::marker::PhantomData;
-
-use halo2_proofs::{
-    plonk::*,
-    circuit::*,
-    arithmetic::FieldExt, poly::Rotation
-};
-
-// create a submodule which is my table and use that
-mod table;
-use table::RangeCheckTable;
-
-#[derive(Debug, Clone)]
-/// A range-constrained value in the circuit produced by the RangeCheckConfig.
-struct RangeConstrained<F: FieldExt, const RANGE: usize>(AssignedCell<Assigned<F>, F>);
-
-#[derive(Debug, Clone)]
-
-// Now we add our RangeCheckTable to our config
-struct RangeCheckConfig<F:FieldExt, const RANGE: usize, const LOOKUP_RANGE: usize> {
-    value: Column<Advice>,
-    q_range_check: Selector,
-    q_lookup: Selector,
-    table: RangeCheckTable<F, LOOKUP_RANGE>
-}
-
-// Write the gate for our range check Config
-// It's good practive to pass advice columns to the config (rather than creating it within the config)
-// because these are very likely to be shared across multiple config
-impl<F: FieldExt, const RANGE: usize, const LOOKUP_RANGE: usize> RangeCheckConfig<F, RANGE, LOOKUP_RANGE> {
-
-    // REMEMBER THAT THE CONFIGURATION HAPPEN AT KEYGEN TIME
-    fn configure(
-        meta: &mut ConstraintSystem<F>,
-        value: Column<Advice>
-    ) -> Self {
-        // Toggles the range check constraint
-        let q_range_check = meta.selector();
-
-        // Toggles the lookup argument
-        // I use a complex selector for that. 
-        // Simple selector cannot appear in lookup arguments.
-        let q_lookup = meta.complex_selector();
-
-        // We also need to configure our look up table and pass it to config
-        let table =   RangeCheckTable::configure(meta);
-
-        let config = Self {
-            q_range_check,
-            q_lookup,
-            value,
-            table: table.clone()
-        }; 
-
-        // range-check gate
-        // For a value v and a range R, check that v < R
-        // v * (1 - v) * (2 - v) * ... (R - 1 - v) = 0 if v is any of these values! 
-        meta.create_gate("range check", |meta| {
-            let q_range_check = meta.query_selector(q_range_check);
-            // note that we don't need to specify the rotation when querying the selctor
-            // That's because the selector always get queried at the current row
-            // While the advice columns get queried relatively to the selector offset, so we need to specify the relative rotation
-            let value = meta.query_advice(value,Rotation::cur());
-
-            // This is a closure that produce the expression defined previously 
-            let range_check = |range: usize, value: Expression<F>| {
-                (0..range).fold(value.clone(), |expr: Expression<F>, i: usize| {
-                    expr * (Expression::Constant(F::from(i as u64)) - value.clone())
-                })
-            };
-            // This is a way to return the constrain from our create_gate function. 
-            // similar to what we were doing previously using "vec![s * (a + b - c)]"
-            // this api, behind the scene, multiplies the specified constraint by the selector
-            Constraints::with_selector(q_range_check, [("range check", range_check(RANGE, value))])
-        });
-
-        // range-check using lookup argument
-        // Check that a value is contained within a lookup table of values 0..RANGE (exclusive)
-        // api to instantiate a lookup argument
-        // Similar to create gate as an api so we need to query the selector and our value
-        meta.lookup(|meta| {
-            let q_lookup = meta.query_selector(q_lookup);
-            let value = meta.query_advice(value,Rotation::cur());
-
-            // The meta.lookup api expect to return a vector of tuples, where the first element
-            // is what you are looking at, and the second element is the corresponding table we are looking into
-            vec![
-                (q_lookup * value, table.value)
-            ]
-        });
-
-        config
-    }
-
-    // assign value to each cell inside the advise column
-    // we can modify this assign function such that under a certain range enables the simple range check expression
-    // and over a certain range enables the look up argument
-    // the range passed in is the actual claimed range
-    fn assign(
-        &self,
-        mut layouter: impl Layouter<F>,
-        value: Value<Assigned<F>>,
-        range: usize
-    ) -> Result<(), Error> {
-
-        assert!(range <= LOOKUP_RANGE);
-
-        if (range < RANGE) {
-            layouter.assign_region(|| "Assign value", |mut region| {
-
-                let offset = 0;
-    
-                // Enable q range check
-                self.q_range_check.enable(&mut region, offset)?;
-
-                // assign given value and return RangeConstrained struct
-                region.assign_advice(
-                    || "assign value",
-                    self.value,
-                    offset,
-                    || value
-                )?;
-
-                Ok(())
-            })
-        } else {
-            layouter.assign_region(|| "Assign value for lookup range check", |mut region| {
-
-                let offset = 0;
-    
-                // Enable q range check
-                self.q_lookup.enable(&mut region, offset)?;
-
-                // assign given value and return RangeConstrained struct
-                region.assign_advice(
-                    || "assign value",
-                    self.value,
-                    offset,
-                    || value
-                )?;
-
-                Ok(())
-        })
-
-}
-
-}
-
-}
+// create a submodule which is my table, ByteDecompositionChip still uses it
+pub(crate) mod table;
 
 // Now let's test it! Here we define a circuit with a single value. and in syntesize function we assign that value
 #[cfg(test)]
 mod tests {
+    use std::marker::PhantomData;
+
     use halo2_proofs::{
-        circuit::floor_planner::V1,
-        dev::{FailureLocation, MockProver, VerifyFailure},
+        arithmetic::FieldExt,
+        circuit::{floor_planner::V1, Layouter, Value},
+        dev::MockProver,
         pasta::Fp,
-        plonk::{Any, Circuit},
+        plonk::{Assigned, Circuit, ConstraintSystem, Error},
     };
 
-    use super::*;
+    use crate::dev_tools::{min_k, prove_and_verify};
+    use crate::range_check::{RangeCheckChip, RangeCheckInstructions};
 
-    #[derive(Default)]
-    struct MyCircuit<F: FieldExt, const RANGE: usize, const LOOKUP_RANGE: usize> {
+    // The circuit is generic over the range check R, like FunctionChip of example3 is over the IsZero gadget
+    struct MyCircuit<F: FieldExt, R: RangeCheckInstructions<F>, const RANGE: usize, const LOOKUP_RANGE: usize> {
         value: Value<Assigned<F>>,
-        large_value: Value<Assigned<F>>
+        large_value: Value<Assigned<F>>,
+        _marker: PhantomData<R>,
     }
 
-    impl<F: FieldExt, const RANGE: usize, const LOOKUP_RANGE: usize> Circuit<F> for MyCircuit<F, RANGE, LOOKUP_RANGE> {
-        type Config = RangeCheckConfig<F, RANGE, LOOKUP_RANGE>;
+    impl<F: FieldExt, R: RangeCheckInstructions<F>, const RANGE: usize, const LOOKUP_RANGE: usize> Circuit<F>
+        for MyCircuit<F, R, RANGE, LOOKUP_RANGE>
+    {
+        type Config = R::Config;
         type FloorPlanner = V1;
 
         fn without_witnesses(&self) -> Self {
-            Self::default()
+            Self {
+                value: Value::unknown(),
+                large_value: Value::unknown(),
+                _marker: PhantomData,
+            }
         }
 
         fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
             let value = meta.advice_column();
-            RangeCheckConfig::configure(meta, value)
+            R::configure(meta, value)
         }
 
         fn synthesize(
@@ -195,20 +61,34 @@ mod tests {
             config: Self::Config,
             mut layouter: impl Layouter<F>,
         ) -> Result<(), Error> {
-            config.assign(layouter.namespace(|| "Assign value"), self.value, RANGE)?;
-            config.assign(layouter.namespace(|| "Assign value"), self.value, LOOKUP_RANGE)?;
+            let chip = R::construct(config);
+            chip.range_check(layouter.namespace(|| "Assign value"), self.value, RANGE)?;
+            chip.range_check(layouter.namespace(|| "Assign large value"), self.large_value, LOOKUP_RANGE)?;
             // We need to load the values inside the lookup table! 
-            config.table.load(&mut layouter)?;
+            chip.load(&mut layouter)?;
             Ok(())
         }
     }
 
+    // RangeCheckChip with an 8-bit lookup table: RANGE uses the range-check expression, LOOKUP_RANGE the lookup
+    fn my_circuit<const RANGE: usize, const LOOKUP_RANGE: usize>(
+        value: Value<Fp>,
+        large_value: Value<Fp>,
+    ) -> MyCircuit<Fp, RangeCheckChip<Fp, RANGE, 8, LOOKUP_RANGE>, RANGE, LOOKUP_RANGE> {
+        MyCircuit {
+            value: value.map(Assigned::from),
+            large_value: large_value.map(Assigned::from),
+            _marker: PhantomData,
+        }
+    }
+
     #[test]
     fn test_range_check_2() {
         // our lookup table is 256 rows + last few rows or the advise colums 
         // are automatically allocated to random values which are bliding factors
-        // so we need to use k=9
-        let k = 9;
+        // so we need to use k=9, min_k finds it for us
+        let k = min_k(&my_circuit::<8, 256>(Value::unknown(), Value::unknown()), &[]).unwrap();
+        assert_eq!(k, 9);
         const RANGE: usize = 8; // 3-bit value table
         const LOOKUP_RANGE: usize = 256; // 8-bit value table
 
@@ -216,53 +96,73 @@ mod tests {
         // Successful cases value=0,1,2,3,4,5,6,7
         // Successful cases large_value=0,1,2,3,4,5,6,7 (these should also pass the lookup range check)
         for i in 0..RANGE {
-            let circuit = MyCircuit::<Fp, RANGE, LOOKUP_RANGE> {
-                value: Value::known(Fp::from(i as u64).into()),
-                large_value : Value::known(Fp::from(i as u64).into())
-            };
+            let circuit = my_circuit::<RANGE, LOOKUP_RANGE>(Value::known(Fp::from(i as u64)), Value::known(Fp::from(i as u64)));
 
             let prover = MockProver::run(k, &circuit, vec![]).unwrap();
             prover.assert_satisfied();
+            crate::dev_tools::assert_fully_constrained(k, &circuit, vec![]);
         }
 
-        // // Out-of-range `value = 8`
-        // {
-        //     let circuit = MyCircuit::<Fp, RANGE> {
-        //         value: Value::known(Fp::from(RANGE as u64).into()),
-        //     };
-        //     let prover = MockProver::run(k, &circuit, vec![]).unwrap();
-        //     // prover.assert_satisfied(); // this should fail!
-        //     assert_eq!(
-        //         prover.verify(),
-        //         Err(vec![VerifyFailure::ConstraintNotSatisfied {
-        //             constraint: ((0, "range check").into(), 0, "range check").into(),
-        //             location: FailureLocation::InRegion {
-        //                 region: (0, "Assign value").into(),
-        //                 offset: 0
-        //             },
-        //             cell_values: vec![(((Any::Advice, 0).into(), 0).into(), "0x8".to_string())]
-        //         }])
-        //     );
-        // }
+        // large_value = 255 passes the lookup but 256 doesn't
+        let circuit = my_circuit::<RANGE, LOOKUP_RANGE>(Value::known(Fp::one()), Value::known(Fp::from(LOOKUP_RANGE as u64 - 1)));
+        MockProver::run(k, &circuit, vec![]).unwrap().assert_satisfied();
+
+        let circuit = my_circuit::<RANGE, LOOKUP_RANGE>(Value::known(Fp::one()), Value::known(Fp::from(LOOKUP_RANGE as u64)));
+        assert!(MockProver::run(k, &circuit, vec![]).unwrap().verify().is_err());
+    }
+
+    #[test]
+    fn test_range_check_2_forged_witness() {
+        use crate::dev_tools::{Forgeries, Rejection};
+
+        let forgeries = Forgeries::new(
+            || my_circuit::<8, 256>(Value::known(Fp::from(7)), Value::known(Fp::from(255))),
+            vec![],
+        );
+
+        // values swapped for out-of-range ones after the honest assignment, column 0 is value
+        forgeries
+            .forge("Assign value", 0, 0, Fp::from(8))
+            .assert_rejected(Rejection::Gate("range check"));
+        forgeries
+            .forge("Assign value for lookup range check", 0, 0, Fp::from(256))
+            .assert_rejected(Rejection::Lookup(0));
+    }
+
+    #[test]
+    fn test_range_check_2_cost() {
+        let circuit = my_circuit::<8, 256>(Value::unknown(), Value::unknown());
+        let report = crate::dev_tools::cost_report(&circuit, &[]).unwrap();
+
+        // the k = 9 of test_range_check_2: the 256 rows of the table don't leave room for the blinding rows in 2^8
+        assert_eq!(report.used_rows, 256, "{}", report);
+        assert!(report.used_rows + report.blinding_rows > 1 << 8, "{}", report);
+        assert_eq!(report.k, 9);
+        // the lookup and the tagged lookup of the chip, and the bound of the tagged lookup
+        assert_eq!(report.lookups, 3);
+    }
+
+    #[test]
+    fn test_range_check_2_prove() {
+        // a real proof, at the k found by min_k
+        let circuit = my_circuit::<8, 256>(Value::known(Fp::from(7)), Value::known(Fp::from(255)));
+        prove_and_verify(&circuit, &[]).unwrap();
     }
 
     #[cfg(feature = "dev-graph")]
     #[test]
     fn print_range_check_2() {
-        use plotters::prelude::*;
+        use crate::dev_tools::{layout_path, render_layout_with, LayoutFormat, LayoutOptions};
 
-        let root = BitMapBackend::new("range-check-2-layout.png", (1024, 3096)).into_drawing_area();
-        root.fill(&WHITE).unwrap();
-        let root = root
-            .titled("Range Check 2 Layout", ("sans-serif", 60))
-            .unwrap();
-
-        let circuit = MyCircuit::<Fp, 8, 256> {
-            value: Value::unknown(),
-            large_value: Value::unknown()
+        let circuit = my_circuit::<8, 256>(Value::unknown(), Value::unknown());
+        // the table fills the usable rows, the blinding rows would only add a gray band
+        let options = LayoutOptions {
+            hide_blinding_rows: true,
+            ..LayoutOptions::default()
         };
-        halo2_proofs::dev::CircuitLayout::default()
-            .render(3, &circuit, &root)
-            .unwrap();
+        let k = min_k(&circuit, &[]).unwrap();
+        for format in [LayoutFormat::Png, LayoutFormat::Svg] {
+            render_layout_with(&circuit, k, layout_path("range-check-2-layout", format), format, &options).unwrap();
+        }
     }
-} 
\ No newline at end of file
+}
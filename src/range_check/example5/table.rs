@@ -10,15 +10,15 @@ use halo2_proofs::{plonk::{Error, TableColumn, ConstraintSystem}, arithmetic::Fi
 // This is a table with a single column. 
 // TableColumn is a Fixed Column
 #[derive(Debug, Clone)]
-pub(super) struct RangeCheckTable<F:FieldExt, const RANGE: usize> {
-    pub(super) value: TableColumn,
+pub(crate) struct RangeCheckTable<F:FieldExt, const RANGE: usize> {
+    pub(crate) value: TableColumn,
     _marker: PhantomData<F>
 }
 
 impl<F:FieldExt, const RANGE: usize> RangeCheckTable<F, RANGE> {
 
     // create a configure function to allow to configure the table in the first place
-    pub(super) fn configure(
+    pub(crate) fn configure(
         meta: &mut ConstraintSystem<F>
     ) -> Self {
         // API to create this special fixed colum
@@ -32,7 +32,7 @@ impl<F:FieldExt, const RANGE: usize> RangeCheckTable<F, RANGE> {
 
     // load function assign the values to our fixed table
     // This action is performed at key gen time
-    pub(super) fn load(
+    pub(crate) fn load(
          &self,
          layouter: &mut impl Layouter<F>
     ) -> Result<(), Error> {
@@ -2,207 +2,68 @@
 // It includes a further lookup table that contains a value num_bits. 
 // For example it can be that our range is 8 bits, but we want to perform a range check on 4 bits.
 // That's why we need this optimization.
-
-use std::marker::PhantomData;
-
-use halo2_proofs::{
-    plonk::*,
-    circuit::*,
-    arithmetic::FieldExt, poly::Rotation
-};
-
-// create a submodule which is my table and use that
-mod table;
-use table::RangeCheckTable;
-
-#[derive(Debug, Clone)]
-/// A range-constrained value in the circuit produced by the RangeCheckConfig.
-struct RangeConstrained<F: FieldExt, const RANGE: usize>(AssignedCell<Assigned<F>, F>);
-
-#[derive(Debug, Clone)]
-
-// WE ADD A FURTHER NUM_BITS COLUMN TO OUR CONFIG
-struct RangeCheckConfig<F:FieldExt, const RANGE: usize, const LOOKUP_NUMBITS: usize, const LOOKUP_RANGE: usize> {
-    value: Column<Advice>,
-    num_bits: Column<Advice>,
-    q_range_check: Selector,
-    q_lookup: Selector,
-    table: RangeCheckTable<F, LOOKUP_NUMBITS, LOOKUP_RANGE>
-}
-
-// Write the gate for our range check Config
-// It's good practive to pass advice columns to the config (rather than creating it within the config)
-// because these are very likely to be shared across multiple config
-impl<F: FieldExt, const RANGE: usize, const LOOKUP_NUMBITS: usize, const LOOKUP_RANGE: usize> RangeCheckConfig<F, RANGE, LOOKUP_NUMBITS, LOOKUP_RANGE> {
-
-    // REMEMBER THAT THE CONFIGURATION HAPPEN AT KEYGEN TIME
-    fn configure(
-        meta: &mut ConstraintSystem<F>,
-        value: Column<Advice>,
-        num_bits: Column<Advice> 
-    ) -> Self {
-        // Toggles the range check constraint
-        let q_range_check = meta.selector();
-
-        // Toggles the lookup argument
-        // I use a complex selector for that. 
-        // Simple selector cannot appear in lookup arguments.
-        let q_lookup = meta.complex_selector();
-
-        // We also need to configure our look up table and pass it to config
-        let table =   RangeCheckTable::configure(meta);
-
-        let config = Self {
-            q_range_check,
-            q_lookup,
-            value,
-            num_bits,
-            table: table.clone()
-        }; 
-
-        // range-check gate
-        // For a value v and a range R, check that v < R
-        // v * (1 - v) * (2 - v) * ... (R - 1 - v) = 0 if v is any of these values! 
-        meta.create_gate("range check", |meta| {
-            let q_range_check = meta.query_selector(q_range_check);
-            // note that we don't need to specify the rotation when querying the selctor
-            // That's because the selector always get queried at the current row
-            // While the advice columns get queried relatively to the selector offset, so we need to specify the relative rotation
-            let value = meta.query_advice(value,Rotation::cur());
-
-            // This is a closure that produce the expression defined previously 
-            let range_check = |range: usize, value: Expression<F>| {
-                (0..range).fold(value.clone(), |expr: Expression<F>, i: usize| {
-                    expr * (Expression::Constant(F::from(i as u64)) - value.clone())
-                })
-            };
-            // This is a way to return the constrain from our create_gate function. 
-            // similar to what we were doing previously using "vec![s * (a + b - c)]"
-            // this api, behind the scene, multiplies the specified constraint by the selector
-            Constraints::with_selector(q_range_check, [("range check", range_check(RANGE, value))])
-        });
-
-        // range-check using lookup argument
-        // Check that a value is contained within a lookup table of values 0..RANGE (exclusive)
-        // IN THIS EXAMPLE we also want to lookup into the num_bits table
-        // api to instantiate a lookup argument
-        // Similar to create gate as an api so we need to query the selector and our value
-        meta.lookup(|meta| {
-            let q_lookup = meta.query_selector(q_lookup);
-            // we add another advise column from the previous example
-            let num_bits = meta.query_advice(num_bits, Rotation::cur());
-            let value = meta.query_advice(value,Rotation::cur());
-
-            // The meta.lookup api expect to return a vector of tuples, where the first element
-            // is what you are looking at, and the second element is the corresponding table we are looking into
-            // NOW WE ALSO LOOKUP num_bits from the instance column inside the num_bits lookup table
-            vec![
-                (q_lookup.clone() * value, table.value),
-                (q_lookup * num_bits, table.num_bits)
-            ]
-        });
-
-        config
-    }
-
-    // assign value to each cell inside the advise column
-    // we can modify this assign function such that under a certain range enables the simple range check expression
-    // and over a certain range enables the look up argument
-    // the range passed in is the actual claimed range
-    fn assign(
-        &self,
-        mut layouter: impl Layouter<F>,
-        value: Value<Assigned<F>>,
-        num_bits: usize,
-        range: usize
-    ) -> Result<(), Error> {
-
-        assert!(range <= LOOKUP_RANGE);
-
-        if range < RANGE {
-            layouter.assign_region(|| "Assign value", |mut region| {
-
-                let offset = 0;
-    
-                // Enable q range check
-                self.q_range_check.enable(&mut region, offset)?;
-
-                // assign num bits
-                region.assign_advice(
-                    || "assign num_bits",
-                    self.num_bits,
-                    offset,
-                    || Value::known(F::from(num_bits as u64))
-                )?;
-
-                // assign given value and return RangeConstrained struct
-                region.assign_advice(
-                    || "assign value",
-                    self.value,
-                    offset,
-                    || value
-                )?;
-
-                Ok(())
-            })
-        } else {
-            layouter.assign_region(|| "Assign value for lookup range check", |mut region| {
-
-                let offset = 0;
-    
-                // Enable q range check
-                self.q_lookup.enable(&mut region, offset)?;
-
-                // assign given value and return RangeConstrained struct
-                region.assign_advice(
-                    || "assign value",
-                    self.value,
-                    offset,
-                    || value
-                )?;
-
-                Ok(())
-        })
-
-}
-
-}
-
-}
+// The bit bound can also be a public input: the verifier learns that value < 2^k for a public k, without learning the value.
+// We look up the exact bit length b of the value and look up k - b in the value column of the table: 0 <= k - b < 2^LOOKUP_NUMBITS
+// The public k isn't limited to the bits of the table, any k up to 2^LOOKUP_NUMBITS (256 here) passes for every v < 2^k
+//   value  | num_bits | bound | q_range_check | q_tagged_lookup
+//   v      |          |       | 1             | 0
+//   v'     | b        | k'    | 0             | 1      <- k' is a constant
+//   v''    | b        | k     | 0             | 1      <- k is copied from the instance column
+// The tagged lookup now lives in RangeCheckChip (see chip.rs) and the table in table.rs, this example uses the tagged lookup mode
 
 // Now let's test it! Here we define a circuit with a single value. and in syntesize function we assign that value
 #[cfg(test)]
 mod tests {
-    use core::num;
-
     use halo2_proofs::{
-        circuit::floor_planner::V1,
-        dev::{FailureLocation, MockProver, VerifyFailure},
+        arithmetic::FieldExt,
+        circuit::{floor_planner::V1, Layouter, Value},
         pasta::Fp,
-        plonk::{Any, Circuit},
+        plonk::{Advice, Assigned, Circuit, Column, ConstraintSystem, Error, Instance},
     };
 
-    use super::*;
+    use crate::dev_tools::{min_k, mock_prove};
+    use crate::range_check::{RangeCheckChip, RangeCheckConfig, RangeCheckMode};
+
+    #[derive(Debug, Clone)]
+    struct MyConfig<F: FieldExt, const RANGE: usize, const LOOKUP_NUMBITS: usize, const LOOKUP_RANGE: usize> {
+        bound: Column<Advice>,
+        instance: Column<Instance>,
+        range_check: RangeCheckConfig<F, RANGE, LOOKUP_NUMBITS, LOOKUP_RANGE>,
+    }
 
     #[derive(Default)]
     struct MyCircuit<F: FieldExt, const RANGE: usize, const LOOKUP_NUMBITS: usize, const LOOKUP_RANGE: usize> {
         value: Value<Assigned<F>>,
         large_value_num_bits: Option<usize>,
-        large_value: Value<Assigned<F>>
+        large_value: Value<Assigned<F>>,
+        // value < 2^k where k is the public input
+        bounded_value: Value<Assigned<F>>
     }
 
     impl<F: FieldExt, const RANGE: usize, const LOOKUP_NUMBITS: usize, const LOOKUP_RANGE: usize> Circuit<F> for MyCircuit<F, RANGE, LOOKUP_NUMBITS, LOOKUP_RANGE> {
-        type Config = RangeCheckConfig<F, RANGE, LOOKUP_NUMBITS, LOOKUP_RANGE>;
+        type Config = MyConfig<F, RANGE, LOOKUP_NUMBITS, LOOKUP_RANGE>;
         type FloorPlanner = V1;
 
         fn without_witnesses(&self) -> Self {
-            Self::default()
+            Self {
+                large_value_num_bits: self.large_value_num_bits,
+                ..Self::default()
+            }
         }
 
         fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
             let value = meta.advice_column();
             let num_bits = meta.advice_column();
-            RangeCheckConfig::configure(meta, value, num_bits)
+            let bound = meta.advice_column();
+            let constant = meta.fixed_column();
+            let instance = meta.instance_column();
+            meta.enable_equality(instance);
+
+            MyConfig {
+                bound,
+                instance,
+                range_check: RangeCheckChip::configure(meta, value, num_bits, bound, constant),
+            }
         }
 
         fn synthesize(
@@ -210,10 +71,26 @@ mod tests {
             config: Self::Config,
             mut layouter: impl Layouter<F>,
         ) -> Result<(), Error> {
-            config.assign(layouter.namespace(|| "Assign value"), self.value, 0, RANGE)?;
-            config.assign(layouter.namespace(|| "Assign value"), self.value, self.large_value_num_bits.unwrap(), LOOKUP_RANGE)?;
+            let chip = RangeCheckChip::construct(config.range_check);
+
+            // the public bound is copied from the instance column
+            let bound = layouter.assign_region(|| "Assign public bound", |mut region| {
+                region.assign_advice_from_instance(|| "bound", config.instance, 0, config.bound, 0)
+            })?;
+
+            chip.assign(layouter.namespace(|| "Assign value"), self.value, RangeCheckMode::Polynomial)?;
+            chip.assign(
+                layouter.namespace(|| "Assign large value"),
+                self.large_value,
+                RangeCheckMode::TaggedLookup { num_bits: self.large_value_num_bits.unwrap_or(LOOKUP_NUMBITS) }
+            )?;
+            chip.assign(
+                layouter.namespace(|| "Assign bounded value"),
+                self.bounded_value,
+                RangeCheckMode::TaggedLookupWithBound(&bound)
+            )?;
             // We need to load the values inside the lookup table! 
-            config.table.load(&mut layouter)?;
+            chip.load_table(&mut layouter)?;
             Ok(())
         }
     }
@@ -222,67 +99,97 @@ mod tests {
     fn test_range_check_3() {
         // our lookup table is 256 rows + last few rows or the advise colums 
         // are automatically allocated to random values which are bliding factors
-        // so we need to use k=9
-        let k = 9;
+        // so k=9 is the smallest one, mock_prove finds it
         const RANGE: usize = 8; // 3-bit value table
         const LOOKUP_NUMBITS: usize = 8; // 8-bit value table 
         const LOOKUP_RANGE: usize = 256; // 8-bit value table
 
-        let circuit = MyCircuit::<Fp, RANGE, LOOKUP_NUMBITS, LOOKUP_RANGE> {
+        let circuit = |bounded_value: u64| MyCircuit::<Fp, RANGE, LOOKUP_NUMBITS, LOOKUP_RANGE> {
             value: Value::known(Fp::one().into()),
             large_value_num_bits: Some(4), // 8 which is 4 bits
-            large_value: Value::known(Fp::from(8 as u64).into())
+            large_value: Value::known(Fp::from(8 as u64).into()),
+            bounded_value: Value::known(Fp::from(bounded_value).into())
         };
 
-        // // Successful cases large_value=0,1,2,3,4,5,6,7 (these should also pass the lookup range check)
-        // for i in 0..RANGE {
-        //     let circuit = MyCircuit::<Fp, RANGE, LOOKUP_NUMBITS, LOOKUP_RANGE> {
-        //         value: Value::known(Fp::from(i as u64).into()),
-        //         large_value : Value::known(Fp::from(i as u64).into())
-        //     };
+        // 8 < 2^4 and 8 < 2^6
+        for k_bound in [4, 6] {
+            let instances = vec![vec![Fp::from(k_bound)]];
+            mock_prove(&circuit(8), instances.clone()).unwrap().assert_satisfied();
+            // num_bits is constrained by the tagged lookup on every path that assigns it
+            let k = min_k(&circuit(8), &instances).unwrap();
+            crate::dev_tools::assert_fully_constrained(k, &circuit(8), instances);
+        }
 
-        //     let prover = MockProver::run(k, &circuit, vec![]).unwrap();
-        //     prover.assert_satisfied(); 
-        // }
+        // 8 is not less than 2^3
+        let prover = mock_prove(&circuit(8), vec![vec![Fp::from(3)]]).unwrap();
+        assert!(prover.verify().is_err());
 
-        // // Out-of-range `value = 8`
-        // {
-        //     let circuit = MyCircuit::<Fp, RANGE> {
-        //         value: Value::known(Fp::from(RANGE as u64).into()),
-        //     };
-        //     let prover = MockProver::run(k, &circuit, vec![]).unwrap();
-        //     // prover.assert_satisfied(); // this should fail!
-        //     assert_eq!(
-        //         prover.verify(),
-        //         Err(vec![VerifyFailure::ConstraintNotSatisfied {
-        //             constraint: ((0, "range check").into(), 0, "range check").into(),
-        //             location: FailureLocation::InRegion {
-        //                 region: (0, "Assign value").into(),
-        //                 offset: 0
-        //             },
-        //             cell_values: vec![(((Any::Advice, 0).into(), 0).into(), "0x8".to_string())]
-        //         }])
-        //     );
-        // }
+        // a public bound above the LOOKUP_NUMBITS of the table is fine too
+        mock_prove(&circuit(8), vec![vec![Fp::from(9)]]).unwrap().assert_satisfied();
+
+        // 8 doesn't fit in 3 bits
+        let mut wrong_num_bits = circuit(8);
+        wrong_num_bits.large_value_num_bits = Some(3);
+        let prover = mock_prove(&wrong_num_bits, vec![vec![Fp::from(4)]]).unwrap();
+        assert!(prover.verify().is_err());
     }
 
-    #[cfg(feature = "dev-graph")]
     #[test]
-    fn print_range_check_2() {
-        use plotters::prelude::*;
+    fn test_range_check_3_forged_witness() {
+        use crate::dev_tools::{Forgeries, Rejection};
 
-        let root = BitMapBackend::new("range-check-2-layout.png", (1024, 3096)).into_drawing_area();
-        root.fill(&WHITE).unwrap();
-        let root = root
-            .titled("Range Check 2 Layout", ("sans-serif", 60))
-            .unwrap();
+        // both tagged lookups share the region name: the first one is the large value with the constant bound 4,
+        // the second one the bounded value with the public bound 6
+        let circuit = || MyCircuit::<Fp, 8, 8, 256> {
+            value: Value::known(Fp::one().into()),
+            large_value_num_bits: Some(4),
+            large_value: Value::known(Fp::from(8).into()),
+            bounded_value: Value::known(Fp::from(8).into()),
+        };
+        let forgeries = Forgeries::new(circuit, vec![vec![Fp::from(6)]]);
+        let tagged = "Assign value for tagged lookup range check";
+
+        // 8 with a bit length of 3, columns 0, 1 and 2 are value, num_bits and bound
+        for nth in [0, 1] {
+            forgeries
+                .forge_nth(tagged, nth, 1, 0, Fp::from(3))
+                .assert_rejected(Rejection::Lookup(1));
+        }
+
+        // 64 with its real bit length 7 is in the table, only the bounds 4 and 6 reject it
+        for nth in [0, 1] {
+            forgeries
+                .forge_nth(tagged, nth, 0, 0, Fp::from(64))
+                .forge_nth(tagged, nth, 1, 0, Fp::from(7))
+                .assert_rejected(Rejection::Lookup(2));
+        }
+
+        // a looser bound than the constant and the public one
+        for nth in [0, 1] {
+            forgeries
+                .forge_nth(tagged, nth, 2, 0, Fp::from(8))
+                .assert_rejected(Rejection::Permutation);
+        }
+    }
 
-        let circuit = MyCircuit::<Fp, 8, 256> {
+    #[cfg(feature = "dev-graph")]
+    #[test]
+    fn print_range_check_3() {
+        use crate::dev_tools::{layout_path, render_layout_with, LayoutFormat, LayoutOptions};
+
+        let circuit = MyCircuit::<Fp, 8, 8, 256> {
             value: Value::unknown(),
-            large_value: Value::unknown()
+            large_value_num_bits: None,
+            large_value: Value::unknown(),
+            bounded_value: Value::unknown()
         };
-        halo2_proofs::dev::CircuitLayout::default()
-            .render(3, &circuit, &root)
-            .unwrap();
+        // the public bound is copied into the tagged lookups, mark the cells that take part in copies
+        let options = LayoutOptions {
+            mark_equality_cells: true,
+            ..LayoutOptions::default()
+        };
+        for format in [LayoutFormat::Png, LayoutFormat::Svg] {
+            render_layout_with(&circuit, 9, layout_path("range-check-3-layout", format), format, &options).unwrap();
+        }
     }
-} 
\ No newline at end of file
+}
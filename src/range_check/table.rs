@@ -6,14 +6,14 @@
 
 use std::marker::PhantomData;
 
-use halo2_proofs::{plonk::{Error, TableColumn, ConstraintSystem}, arithmetic::FieldExt, circuit::{Value, Layouter}, dev::metadata::Constraint};
+use halo2_proofs::{plonk::{Error, TableColumn, ConstraintSystem}, arithmetic::FieldExt, circuit::{Value, Layouter}};
 
 // This is a table with a NOW 2 columns. 
 // TableColumn is a Fixed Column
 #[derive(Debug, Clone)]
-pub(super) struct RangeCheckTable<F:FieldExt, const NUM_BITS: usize, const RANGE: usize> {
-    pub(super) num_bits: TableColumn,
-    pub(super) value: TableColumn,
+pub(crate) struct RangeCheckTable<F:FieldExt, const NUM_BITS: usize, const RANGE: usize> {
+    pub(crate) num_bits: TableColumn,
+    pub(crate) value: TableColumn,
     _marker: PhantomData<F>
 }
 
@@ -21,7 +21,7 @@ impl<F:FieldExt, const NUM_BITS: usize, const RANGE: usize> RangeCheckTable<F, N
 
 
     // create a configure function to allow to configure the table in the first place
-    pub(super) fn configure(
+    pub(crate) fn configure(
         meta: &mut ConstraintSystem<F>
     ) -> Self {
         // check that 2^NUM_BITS = RANGE
@@ -40,7 +40,7 @@ impl<F:FieldExt, const NUM_BITS: usize, const RANGE: usize> RangeCheckTable<F, N
 
     // load function assign the values to our fixed table
     // This action is performed at key gen time
-    pub(super) fn load(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
+    pub(crate) fn load(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
         layouter.assign_table(
             || "load range-check table",
             |mut table| {
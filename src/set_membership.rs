@@ -0,0 +1,339 @@
+// Goal: prove that a witness is (or is not) a member of a small set of constants or assigned cells, without a lookup table.
+// It's the same idea as the range check expression of example4, generalized to arbitrary elements:
+//   (v - s_0) * (v - s_1) * ... * (v - s_n) = 0 if v is any of these elements!
+// A single gate would have a degree that grows with the size of the set, so we split it with a running product instead:
+//   acc_0 = v - s_0
+//   acc_i = acc_{i-1} * (v - s_i)
+// v is a member if the last accumulator is 0, and it's not a member if the last accumulator is not 0.
+// For the latter we use the IsZero gadget on the product and require its output to be 0.
+//
+//   value | element | acc                 | value_inv | q_first | q_acc | q_in | q_not_in
+//   v     | s_0     | v - s_0             |           | 1       | 0     | 0    | 0
+//   v     | s_1     | acc_0 * (v - s_1)   | 1 / acc_1 | 0       | 1     | 0    | 1
+
+use std::marker::PhantomData;
+
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    circuit::{AssignedCell, Layouter, Value},
+    plonk::{Advice, Column, ConstraintSystem, Constraints, Error, Fixed, Selector},
+    poly::Rotation,
+};
+
+use crate::is_zero::{IsZeroChip, IsZeroConfig, IsZeroInstructions};
+
+// An element of the set, either a constant or an assigned cell
+#[derive(Debug, Clone)]
+pub enum Element<'a, F: FieldExt> {
+    Constant(F),
+    Cell(&'a AssignedCell<F, F>),
+}
+
+#[derive(Debug, Clone)]
+pub struct SetMembershipConfig<F: FieldExt> {
+    value: Column<Advice>,
+    element: Column<Advice>,
+    acc: Column<Advice>,
+    q_first: Selector,
+    q_acc: Selector,
+    q_in: Selector,
+    q_not_in: Selector,
+    acc_is_zero: IsZeroConfig<F>,
+}
+
+pub struct SetMembershipChip<F: FieldExt> {
+    config: SetMembershipConfig<F>,
+    _marker: PhantomData<F>,
+}
+
+impl<F: FieldExt> SetMembershipChip<F> {
+    pub fn construct(config: SetMembershipConfig<F>) -> Self {
+        Self {
+            config,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn configure(
+        meta: &mut ConstraintSystem<F>,
+        advice: [Column<Advice>; 4],
+        constant: Column<Fixed>,
+    ) -> SetMembershipConfig<F> {
+        let [value, element, acc, value_inv] = advice;
+        meta.enable_equality(value);
+        meta.enable_equality(element);
+        // constant elements are assigned to the element column and constrained to this fixed column
+        meta.enable_constant(constant);
+
+        let q_first = meta.selector();
+        let q_acc = meta.selector();
+        let q_in = meta.selector();
+        let q_not_in = meta.selector();
+
+        meta.create_gate("set product first", |meta| {
+            let q_first = meta.query_selector(q_first);
+            let value = meta.query_advice(value, Rotation::cur());
+            let element = meta.query_advice(element, Rotation::cur());
+            let acc = meta.query_advice(acc, Rotation::cur());
+            Constraints::with_selector(q_first, [("acc_0 = v - s_0", acc - (value - element))])
+        });
+
+        meta.create_gate("set product", |meta| {
+            let q_acc = meta.query_selector(q_acc);
+            let value_prev = meta.query_advice(value, Rotation::prev());
+            let value = meta.query_advice(value, Rotation::cur());
+            let element = meta.query_advice(element, Rotation::cur());
+            let acc_prev = meta.query_advice(acc, Rotation::prev());
+            let acc = meta.query_advice(acc, Rotation::cur());
+            Constraints::with_selector(
+                q_acc,
+                [
+                    // v is the same on every row
+                    ("same value", value.clone() - value_prev),
+                    ("acc_i = acc_{i-1} * (v - s_i)", acc - acc_prev * (value - element)),
+                ],
+            )
+        });
+
+        meta.create_gate("in set", |meta| {
+            let q_in = meta.query_selector(q_in);
+            let acc = meta.query_advice(acc, Rotation::cur());
+            Constraints::with_selector(q_in, [("product is zero", acc)])
+        });
+
+        // is_zero_expr is 1 if the product is 0
+        let acc_is_zero = IsZeroChip::configure(
+            meta,
+            |meta| meta.query_selector(q_not_in),
+            |meta| meta.query_advice(acc, Rotation::cur()),
+            value_inv,
+        );
+
+        meta.create_gate("not in set", |meta| {
+            let q_not_in = meta.query_selector(q_not_in);
+            Constraints::with_selector(q_not_in, [("product is not zero", acc_is_zero.expr())])
+        });
+
+        SetMembershipConfig {
+            value,
+            element,
+            acc,
+            q_first,
+            q_acc,
+            q_in,
+            q_not_in,
+            acc_is_zero,
+        }
+    }
+
+    // Native reference: true if value is one of the elements
+    pub fn native(value: F, set: &[F]) -> bool {
+        set.contains(&value)
+    }
+
+    // Constrains value to be one of the elements
+    pub fn in_set(&self, layouter: impl Layouter<F>, value: &AssignedCell<F, F>, set: &[Element<'_, F>]) -> Result<(), Error> {
+        self.assign(layouter, value, set, true)
+    }
+
+    // Constrains value to be none of the elements
+    pub fn not_in_set(&self, layouter: impl Layouter<F>, value: &AssignedCell<F, F>, set: &[Element<'_, F>]) -> Result<(), Error> {
+        self.assign(layouter, value, set, false)
+    }
+
+    fn assign(
+        &self,
+        mut layouter: impl Layouter<F>,
+        value: &AssignedCell<F, F>,
+        set: &[Element<'_, F>],
+        member: bool,
+    ) -> Result<(), Error> {
+        assert!(!set.is_empty());
+        let is_zero_chip = IsZeroChip::construct(self.config.acc_is_zero.clone());
+
+        layouter.assign_region(
+            || if member { "in set" } else { "not in set" },
+            |mut region| {
+                let mut acc = Value::known(F::one());
+
+                for (i, element) in set.iter().enumerate() {
+                    if i == 0 {
+                        self.config.q_first.enable(&mut region, i)?;
+                    } else {
+                        self.config.q_acc.enable(&mut region, i)?;
+                    }
+
+                    value.copy_advice(|| "value", &mut region, self.config.value, i)?;
+                    let element = match element {
+                        Element::Constant(constant) => {
+                            region.assign_advice_from_constant(|| "element", self.config.element, i, *constant)?
+                        }
+                        Element::Cell(cell) => cell.copy_advice(|| "element", &mut region, self.config.element, i)?,
+                    };
+
+                    acc = acc
+                        .zip(value.value())
+                        .zip(element.value())
+                        .map(|((acc, value), element)| acc * (*value - element));
+                    region.assign_advice(|| "acc", self.config.acc, i, || acc)?;
+                }
+
+                let last = set.len() - 1;
+                if member {
+                    self.config.q_in.enable(&mut region, last)?;
+                } else {
+                    self.config.q_not_in.enable(&mut region, last)?;
+                    is_zero_chip.assign(&mut region, last, acc)?;
+                }
+
+                Ok(())
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use halo2_proofs::{circuit::SimpleFloorPlanner, dev::MockProver, pasta::Fp, plonk::Circuit};
+
+    // checks that `value` is in {1, 5, 9} and in {a, b}, and that `other` is in neither
+    #[derive(Default)]
+    struct MyCircuit<F> {
+        value: F,
+        other: F,
+        a: F,
+        b: F,
+    }
+
+    impl<F: FieldExt> Circuit<F> for MyCircuit<F> {
+        type Config = SetMembershipConfig<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            let advice = [
+                meta.advice_column(),
+                meta.advice_column(),
+                meta.advice_column(),
+                meta.advice_column(),
+            ];
+            let constant = meta.fixed_column();
+            SetMembershipChip::configure(meta, advice, constant)
+        }
+
+        fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<F>) -> Result<(), Error> {
+            let chip = SetMembershipChip::construct(config.clone());
+
+            let [value, other, a, b] = layouter.assign_region(
+                || "load inputs",
+                |mut region| {
+                    let mut load = |row: usize, v: F| region.assign_advice(|| "input", config.value, row, || Value::known(v));
+                    Ok([load(0, self.value)?, load(1, self.other)?, load(2, self.a)?, load(3, self.b)?])
+                },
+            )?;
+
+            let constants = [1, 5, 9].map(|c| Element::Constant(F::from(c)));
+            let cells = [Element::Cell(&a), Element::Cell(&b)];
+
+            chip.in_set(layouter.namespace(|| "value in constants"), &value, &constants)?;
+            chip.in_set(layouter.namespace(|| "value in cells"), &value, &cells)?;
+            chip.not_in_set(layouter.namespace(|| "other not in constants"), &other, &constants)?;
+            chip.not_in_set(layouter.namespace(|| "other not in cells"), &other, &cells)
+        }
+    }
+
+    #[test]
+    fn test_set_membership() {
+        let k = 5;
+
+        let circuit = MyCircuit {
+            value: Fp::from(5),
+            other: Fp::from(4),
+            a: Fp::from(7),
+            b: Fp::from(5),
+        };
+        let prover = MockProver::run(k, &circuit, vec![]).unwrap();
+        prover.assert_satisfied();
+        crate::dev_tools::assert_fully_constrained(k, &circuit, vec![]);
+
+        // 4 is not in {1, 5, 9}
+        let circuit = MyCircuit {
+            value: Fp::from(4),
+            other: Fp::from(4),
+            a: Fp::from(4),
+            b: Fp::from(5),
+        };
+        let prover = MockProver::run(k, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+
+        // 7 is in {a, b}
+        let circuit = MyCircuit {
+            value: Fp::from(5),
+            other: Fp::from(7),
+            a: Fp::from(7),
+            b: Fp::from(5),
+        };
+        let prover = MockProver::run(k, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_set_membership_reference() {
+        use crate::dev_tools::check_acceptance;
+
+        check_acceptance(
+            5,
+            0,
+            32,
+            // small values so that the inputs hit the sets often, value is a constant and a half of the time
+            |rng| {
+                let [mut value, other, mut a, b] = [0; 4].map(|_| Fp::from(rng.below(12) as u64));
+                if rng.below(2) == 0 {
+                    value = Fp::from([1, 5, 9][rng.below(3)]);
+                }
+                if rng.below(2) == 0 {
+                    a = value;
+                }
+                [value, other, a, b]
+            },
+            |&[value, other, a, b]| {
+                let member = |v, set: &[Fp]| SetMembershipChip::native(v, set);
+                let constants = [1, 5, 9].map(Fp::from);
+                member(value, &constants) && member(value, &[a, b]) && !member(other, &constants) && !member(other, &[a, b])
+            },
+            |&[value, other, a, b]| (MyCircuit { value, other, a, b }, vec![]),
+        );
+    }
+
+    #[test]
+    fn test_set_membership_forged_witness() {
+        use crate::dev_tools::{Forgeries, Rejection};
+
+        let circuit = || MyCircuit {
+            value: Fp::from(5),
+            other: Fp::from(4),
+            a: Fp::from(7),
+            b: Fp::from(5),
+        };
+        let forgeries = Forgeries::new(circuit, vec![]);
+
+        // a zero first accumulator, column 2 is acc. The first region of each kind checks the constants {1, 5, 9}
+        forgeries
+            .forge_nth("in set", 0, 2, 0, Fp::zero())
+            .assert_rejected(Rejection::Gate("set product first"));
+
+        // value_inv = 0 claims that the product is zero, column 3 is value_inv
+        forgeries
+            .forge_nth("not in set", 0, 3, 2, Fp::zero())
+            .assert_rejected(Rejection::Gate("is_zero"));
+
+        // switching to a member value halfway, column 0 is value
+        forgeries
+            .forge_nth("not in set", 0, 0, 1, Fp::from(5))
+            .assert_rejected(Rejection::Gate("set product"));
+    }
+}
@@ -0,0 +1,461 @@
+// Goal: example6 showed that a tag column (num_bits) lets a single lookup table serve several ranges.
+// Here we push that idea further: a TableRegistry packs MANY logical tables (byte ranges, nibbles,
+// XOR, AND, custom functions...) into ONE pair of tag/value lookup columns.
+// Every logical table gets its own tag and each chip looks up `(tag, value)` with the tag of the table it cares about.
+// Adding a new table only costs new rows, not new fixed columns!
+//
+//   tag | value
+//   0   | 0                          <- reserved row, this is what a disabled lookup (q_lookup = 0) hits
+//   1   | 0..256                     <- e.g. byte range
+//   2   | 0..16                      <- e.g. nibble range
+//   3   | a + 16 * b + 256 * (a ^ b) <- e.g. 4-bit XOR, the three "columns" are packed into one value
+//
+// Packing is only sound if every component but the last one is range checked: otherwise a malicious prover
+// could pick a' = a + 16, b' = b - 1 and hit the same packed value. That's why every packed table
+// automatically registers the range table of its limb size and `lookup` also checks the components against it.
+//
+// BitwiseChip and FunctionLookupChip take the registry in their `configure`: they register their tables,
+// look them up by tag and let whoever owns the registry `load` it once. A circuit using both of them (or two
+// functions) pays for a single pair of table columns and for the nibble range only once.
+
+use std::marker::PhantomData;
+
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    circuit::{Layouter, Value},
+    plonk::{ConstraintSystem, Error, Expression, TableColumn, VirtualCells},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// The tag identifying a logical table inside the registry.
+pub struct TableTag(u64);
+
+impl TableTag {
+    pub fn expr<F: FieldExt>(&self) -> Expression<F> {
+        Expression::Constant(F::from(self.0))
+    }
+}
+
+#[derive(Debug, Clone)]
+/// A logical table that can be packed into the registry.
+pub enum LogicalTable {
+    // values 0..2^num_bits
+    Range { num_bits: usize },
+    // (a, b, a ^ b) for a, b in 0..2^num_bits
+    Xor { num_bits: usize },
+    // (a, b, a & b) for a, b in 0..2^num_bits
+    And { num_bits: usize },
+    // (a, b, a | b) for a, b in 0..2^num_bits
+    Or { num_bits: usize },
+    // (a, 0, !a) for a in 0..2^num_bits, NOT has a single input so b is always 0
+    Not { num_bits: usize },
+    // (x, f(x)) for x in 0..2^num_bits
+    Function {
+        name: &'static str,
+        num_bits: usize,
+        f: fn(u64) -> u64,
+    },
+}
+
+impl LogicalTable {
+    pub fn name(&self) -> String {
+        match self {
+            LogicalTable::Range { num_bits } => format!("range {}", num_bits),
+            LogicalTable::Xor { num_bits } => format!("xor {}", num_bits),
+            LogicalTable::And { num_bits } => format!("and {}", num_bits),
+            LogicalTable::Or { num_bits } => format!("or {}", num_bits),
+            LogicalTable::Not { num_bits } => format!("not {}", num_bits),
+            LogicalTable::Function { name, num_bits, .. } => format!("{} {}", name, num_bits),
+        }
+    }
+
+    pub fn num_bits(&self) -> usize {
+        match self {
+            LogicalTable::Range { num_bits }
+            | LogicalTable::Xor { num_bits }
+            | LogicalTable::And { num_bits }
+            | LogicalTable::Or { num_bits }
+            | LogicalTable::Not { num_bits }
+            | LogicalTable::Function { num_bits, .. } => *num_bits,
+        }
+    }
+
+    // number of components packed into a single value
+    pub fn arity(&self) -> usize {
+        match self {
+            LogicalTable::Range { .. } => 1,
+            LogicalTable::Xor { .. } | LogicalTable::And { .. } | LogicalTable::Or { .. } | LogicalTable::Not { .. } => 3,
+            LogicalTable::Function { .. } => 2,
+        }
+    }
+
+    // Every row of the table, with its components already packed as x_0 + 2^n * x_1 + 2^2n * x_2
+    fn values(&self) -> Vec<u64> {
+        let n = self.num_bits();
+        let range = 0..(1u64 << n);
+        match self {
+            LogicalTable::Range { .. } => range.collect(),
+            LogicalTable::Xor { .. } => range
+                .clone()
+                .flat_map(|a| range.clone().map(move |b| pack_u64(n, &[a, b, a ^ b])))
+                .collect(),
+            LogicalTable::And { .. } => range
+                .clone()
+                .flat_map(|a| range.clone().map(move |b| pack_u64(n, &[a, b, a & b])))
+                .collect(),
+            LogicalTable::Or { .. } => range
+                .clone()
+                .flat_map(|a| range.clone().map(move |b| pack_u64(n, &[a, b, a | b])))
+                .collect(),
+            LogicalTable::Not { .. } => range.map(|a| pack_u64(n, &[a, 0, !a & ((1 << n) - 1)])).collect(),
+            LogicalTable::Function { f, .. } => range.map(|x| pack_u64(n, &[x, f(x)])).collect(),
+        }
+    }
+}
+
+// f(x) of a Function table isn't bounded by num_bits, so its packed value can still overflow
+fn pack_u64(num_bits: usize, components: &[u64]) -> u64 {
+    components.iter().rev().fold(0, |acc, component| {
+        acc.checked_mul(1 << num_bits)
+            .and_then(|acc| acc.checked_add(*component))
+            .expect("packed value doesn't fit in a u64")
+    })
+}
+
+// Pack the components of a lookup the same way the table rows are packed
+pub fn pack<F: FieldExt>(num_bits: usize, components: Vec<Expression<F>>) -> Expression<F> {
+    let shift = F::from(1u64 << num_bits);
+    components
+        .into_iter()
+        .rev()
+        .fold(Expression::Constant(F::zero()), |acc, component| {
+            acc * Expression::Constant(shift) + component
+        })
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    tag: TableTag,
+    table: LogicalTable,
+    // tag of the range table used to check the components of a packed table
+    range_tag: Option<TableTag>,
+}
+
+#[derive(Debug, Clone)]
+// Register every table BEFORE handing the registry to the chips,
+// the tags are known at configuration time but the rows are only loaded once in `load`
+pub struct TableRegistry<F: FieldExt> {
+    pub tag: TableColumn,
+    pub value: TableColumn,
+    entries: Vec<Entry>,
+    _marker: PhantomData<F>,
+}
+
+impl<F: FieldExt> TableRegistry<F> {
+    pub fn configure(meta: &mut ConstraintSystem<F>) -> Self {
+        let tag = meta.lookup_table_column();
+        let value = meta.lookup_table_column();
+        Self {
+            tag,
+            value,
+            entries: vec![],
+            _marker: PhantomData,
+        }
+    }
+
+    // Register a logical table and get back its tag. Registering the same table twice returns the same tag
+    // so that chips sharing a table (e.g. a byte range) don't pay for it twice.
+    // Tables are keyed by name and width, two functions with the same key must have the same rows.
+    pub fn register(&mut self, table: LogicalTable) -> TableTag {
+        if let Some(entry) = self.entries.iter().find(|entry| entry.table.name() == table.name()) {
+            assert!(
+                entry.table.values() == table.values(),
+                "a different table is already registered as '{}'",
+                table.name()
+            );
+            return entry.tag;
+        }
+
+        assert!(table.arity() * table.num_bits() <= 48, "packed table is too large");
+        // the width check doesn't bound f(x) of a Function table, packing every row now makes an overflow
+        // fail at registration instead of in `rows` or `load`
+        table.values();
+        let range_tag = if table.arity() > 1 {
+            Some(self.register(LogicalTable::Range {
+                num_bits: table.num_bits(),
+            }))
+        } else {
+            None
+        };
+
+        // tag 0 is reserved for the padding row
+        let tag = TableTag(self.entries.len() as u64 + 1);
+        self.entries.push(Entry {
+            tag,
+            table,
+            range_tag,
+        });
+        tag
+    }
+
+    pub fn table(&self, tag: TableTag) -> &LogicalTable {
+        &self.entry(tag).table
+    }
+
+    fn entry(&self, tag: TableTag) -> &Entry {
+        self.entries
+            .iter()
+            .find(|entry| entry.tag == tag)
+            .expect("table is not registered")
+    }
+
+    // Total number of rows used by the registry (including the padding row)
+    pub fn rows(&self) -> usize {
+        1 + self
+            .entries
+            .iter()
+            .map(|entry| entry.table.values().len())
+            .sum::<usize>()
+    }
+
+    // Look up `values` in the table identified by `tag`.
+    // q_enable and values are closures, just like in IsZeroChip::configure.
+    // For a packed table `values` has one expression per component, e.g. [a, b, a ^ b] for XOR.
+    pub fn lookup(
+        &self,
+        meta: &mut ConstraintSystem<F>,
+        tag: TableTag,
+        q_enable: impl Fn(&mut VirtualCells<'_, F>) -> Expression<F>,
+        values: impl Fn(&mut VirtualCells<'_, F>) -> Vec<Expression<F>>,
+    ) {
+        let entry = self.entry(tag).clone();
+        let num_bits = entry.table.num_bits();
+        let arity = entry.table.arity();
+
+        // every component but the last one is range checked, so the packing can't be forged
+        if let Some(range_tag) = entry.range_tag {
+            for i in 0..arity - 1 {
+                meta.lookup(|meta| {
+                    let q_enable = q_enable(meta);
+                    let value = values(meta).swap_remove(i);
+                    vec![
+                        (q_enable.clone() * range_tag.expr(), self.tag),
+                        (q_enable * value, self.value),
+                    ]
+                });
+            }
+        }
+
+        meta.lookup(|meta| {
+            let q_enable = q_enable(meta);
+            let values = values(meta);
+            assert_eq!(values.len(), arity, "wrong number of components for {}", entry.table.name());
+            vec![
+                (q_enable.clone() * tag.expr(), self.tag),
+                (q_enable * pack(num_bits, values), self.value),
+            ]
+        });
+    }
+
+    // Load every registered table. This action is performed at key gen time
+    pub fn load(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
+        layouter.assign_table(
+            || "load table registry",
+            |mut table| {
+                let mut offset = 0;
+                let mut assign = |tag: u64, value: u64| -> Result<(), Error> {
+                    table.assign_cell(|| "assign tag", self.tag, offset, || Value::known(F::from(tag)))?;
+                    table.assign_cell(|| "assign value", self.value, offset, || Value::known(F::from(value)))?;
+                    offset += 1;
+                    Ok(())
+                };
+
+                // padding row, unused rows of the table are filled with this one too
+                assign(0, 0)?;
+
+                for entry in self.entries.iter() {
+                    for value in entry.table.values() {
+                        assign(entry.tag.0, value)?;
+                    }
+                }
+
+                Ok(())
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use halo2_proofs::{
+        circuit::SimpleFloorPlanner,
+        pasta::Fp,
+        plonk::{Advice, Circuit, Column, Selector},
+        poly::Rotation,
+    };
+    use crate::dev_tools::{min_k, mock_prove};
+
+    #[derive(Debug, Clone)]
+    struct MyConfig<F: FieldExt> {
+        a: Column<Advice>,
+        b: Column<Advice>,
+        c: Column<Advice>,
+        q_byte: Selector,
+        q_xor: Selector,
+        registry: TableRegistry<F>,
+    }
+
+    // checks that `a` is a byte and that c = a ^ b on nibbles
+    #[derive(Default)]
+    struct MyCircuit {
+        a: u64,
+        b: u64,
+        c: u64,
+    }
+
+    impl<F: FieldExt> Circuit<F> for MyCircuit {
+        type Config = MyConfig<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            let a = meta.advice_column();
+            let b = meta.advice_column();
+            let c = meta.advice_column();
+            let q_byte = meta.complex_selector();
+            let q_xor = meta.complex_selector();
+
+            let mut registry = TableRegistry::configure(meta);
+            let byte = registry.register(LogicalTable::Range { num_bits: 8 });
+            let xor = registry.register(LogicalTable::Xor { num_bits: 4 });
+
+            registry.lookup(
+                meta,
+                byte,
+                |meta| meta.query_selector(q_byte),
+                |meta| vec![meta.query_advice(a, Rotation::cur())],
+            );
+            registry.lookup(
+                meta,
+                xor,
+                |meta| meta.query_selector(q_xor),
+                |meta| {
+                    vec![
+                        meta.query_advice(a, Rotation::cur()),
+                        meta.query_advice(b, Rotation::cur()),
+                        meta.query_advice(c, Rotation::cur()),
+                    ]
+                },
+            );
+
+            MyConfig {
+                a,
+                b,
+                c,
+                q_byte,
+                q_xor,
+                registry,
+            }
+        }
+
+        fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<F>) -> Result<(), Error> {
+            layouter.assign_region(
+                || "byte",
+                |mut region| {
+                    config.q_byte.enable(&mut region, 0)?;
+                    region.assign_advice(|| "a", config.a, 0, || Value::known(F::from(self.a)))?;
+                    Ok(())
+                },
+            )?;
+            layouter.assign_region(
+                || "xor",
+                |mut region| {
+                    config.q_xor.enable(&mut region, 0)?;
+                    region.assign_advice(|| "a", config.a, 0, || Value::known(F::from(self.a)))?;
+                    region.assign_advice(|| "b", config.b, 0, || Value::known(F::from(self.b)))?;
+                    region.assign_advice(|| "c", config.c, 0, || Value::known(F::from(self.c)))?;
+                    Ok(())
+                },
+            )?;
+            config.registry.load(&mut layouter)
+        }
+    }
+
+    #[test]
+    fn test_table_registry() {
+        let circuit = MyCircuit { a: 5, b: 9, c: 5 ^ 9 };
+        mock_prove::<Fp, _>(&circuit, vec![]).unwrap().assert_satisfied();
+        // 1 padding row + 256 byte rows + 16 nibble rows + 256 xor rows
+        let k = min_k::<Fp, _>(&circuit, &[]).unwrap();
+        assert_eq!(k, 10);
+        crate::dev_tools::assert_fully_constrained::<Fp, _>(k, &circuit, vec![]);
+
+        // wrong xor output
+        let circuit = MyCircuit { a: 5, b: 9, c: 5 & 9 };
+        let prover = mock_prove::<Fp, _>(&circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+
+        // a' = a + 16, b' = b - 1 gives the same packed value but `a` is not a nibble anymore
+        let circuit = MyCircuit { a: 5 + 16, b: 8, c: 5 ^ 9 };
+        let prover = mock_prove::<Fp, _>(&circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_table_registry_forged_witness() {
+        use crate::dev_tools::{Forgeries, Rejection};
+
+        let forgeries = Forgeries::<Fp, _, _>::new(|| MyCircuit { a: 5, b: 9, c: 5 ^ 9 }, vec![]);
+
+        // 256 is not a byte, lookup 0 is the byte range
+        forgeries
+            .forge("byte", 0, 0, Fp::from(256))
+            .assert_rejected(Rejection::Lookup(0));
+
+        // the packed value is unchanged but a is not a nibble, lookup 1 range checks a
+        forgeries
+            .forge("xor", 0, 0, Fp::from(5 + 16))
+            .forge("xor", 1, 0, Fp::from(8))
+            .assert_rejected(Rejection::Lookup(1));
+    }
+
+    #[test]
+    fn test_register() {
+        let mut meta = ConstraintSystem::<Fp>::default();
+        let mut registry = TableRegistry::configure(&mut meta);
+
+        // the xor table brings its nibble range along, registering that range again doesn't add a table
+        let xor = registry.register(LogicalTable::Xor { num_bits: 4 });
+        let nibble = registry.register(LogicalTable::Range { num_bits: 4 });
+        assert_eq!(registry.entry(xor).range_tag, Some(nibble));
+        let square = |x: u64| x * x;
+        let first = registry.register(LogicalTable::Function { name: "square", num_bits: 4, f: square });
+        let second = registry.register(LogicalTable::Function { name: "square", num_bits: 4, f: square });
+        assert_eq!(first, second);
+        // a different width is a different table
+        assert_ne!(first, registry.register(LogicalTable::Function { name: "square", num_bits: 3, f: square }));
+        assert_eq!(registry.rows(), 1 + 16 + 256 + 16 + 8);
+    }
+
+    #[test]
+    #[should_panic(expected = "a different table is already registered as 'square 4'")]
+    fn test_register_same_name() {
+        let mut meta = ConstraintSystem::<Fp>::default();
+        let mut registry = TableRegistry::configure(&mut meta);
+        registry.register(LogicalTable::Function { name: "square", num_bits: 4, f: |x| x * x });
+        registry.register(LogicalTable::Function { name: "square", num_bits: 4, f: |x| x + x });
+    }
+
+    #[test]
+    #[should_panic(expected = "packed value doesn't fit in a u64")]
+    fn test_register_overflow() {
+        let mut meta = ConstraintSystem::<Fp>::default();
+        let mut registry = TableRegistry::configure(&mut meta);
+        // f(x) isn't bounded by num_bits, 2^8 * u64::MAX overflows
+        registry.register(LogicalTable::Function { name: "max", num_bits: 8, f: |_| u64::MAX });
+    }
+}